@@ -0,0 +1,437 @@
+//! ## Cancel
+//!
+//! a `RemoteFs` wrapper whose operations can be cancelled from another thread
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::stream::{ReadAndSeek, WriteAndSeek};
+use super::{
+    ExecOutput, File, HostKeyVerifier, Metadata, ReadStream, RemoteError, RemoteErrorType,
+    RemoteFs, RemoteResult, UnixPex, Welcome, WriteStream,
+};
+
+/// A token to cancel the operations of a [`CancellableFs`].
+///
+/// Tokens are cheap to clone, and all the clones share the same state,
+/// so a clone can be moved to another thread to cancel the running operation.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new token, which is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the operations using this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the token has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Reset the token, so that new operations can run again
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A [`RemoteFs`] wrapper which stops its operations once its [`CancellationToken`] is cancelled,
+/// returning [`RemoteErrorType::Cancelled`].
+///
+/// The token is checked before each operation and between each chunk read from or written to a stream,
+/// so transfers stop promptly. Methods composed of other operations (e.g. [`RemoteFs::remove_dir_all`])
+/// are run on the wrapper, so they stop before the next underlying operation.
+pub struct CancellableFs<T: RemoteFs> {
+    inner: T,
+    token: CancellationToken,
+}
+
+impl<T: RemoteFs> CancellableFs<T> {
+    /// Wrap `inner` into a new `CancellableFs` cancelled by `token`
+    pub fn new(inner: T, token: CancellationToken) -> Self {
+        Self { inner, token }
+    }
+
+    /// Get the cancellation token
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Get a reference to the inner file system
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner file system
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner file system
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Run `f` on the inner file system, unless the token has been cancelled.
+    /// Errors returned after the token has been cancelled are reported as cancelled
+    fn run<R, F>(&mut self, f: F) -> RemoteResult<R>
+    where
+        F: FnOnce(&mut T) -> RemoteResult<R>,
+    {
+        if self.token.is_cancelled() {
+            return Err(RemoteError::new(RemoteErrorType::Cancelled));
+        }
+        f(&mut self.inner).map_err(|err| self.cancelled_or(err))
+    }
+
+    /// Run `f` on the inner file system, returning a result for each of the `len` requested items,
+    /// unless the token has been cancelled. Errors returned after the token has been cancelled are reported as cancelled
+    fn run_many<R, F>(&mut self, len: usize, f: F) -> Vec<RemoteResult<R>>
+    where
+        F: FnOnce(&mut T) -> Vec<RemoteResult<R>>,
+    {
+        if self.token.is_cancelled() {
+            return (0..len)
+                .map(|_| Err(RemoteError::new(RemoteErrorType::Cancelled)))
+                .collect();
+        }
+        f(&mut self.inner)
+            .into_iter()
+            .map(|result| result.map_err(|err| self.cancelled_or(err)))
+            .collect()
+    }
+
+    /// Report `err` as cancelled if the token has been cancelled
+    fn cancelled_or(&self, err: RemoteError) -> RemoteError {
+        match self.token.is_cancelled() {
+            true => RemoteError::new(RemoteErrorType::Cancelled),
+            false => err,
+        }
+    }
+
+    fn cancellable<S>(&self, stream: S) -> Cancellable<S> {
+        Cancellable {
+            stream,
+            token: self.token.clone(),
+        }
+    }
+}
+
+impl<T: RemoteFs> RemoteFs for CancellableFs<T> {
+    fn connect(&mut self) -> RemoteResult<Welcome> {
+        self.run(|fs| fs.connect())
+    }
+
+    fn set_host_key_verifier(&mut self, verifier: HostKeyVerifier) {
+        self.inner.set_host_key_verifier(verifier)
+    }
+
+    fn disconnect(&mut self) -> RemoteResult<()> {
+        self.inner.disconnect()
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn pwd(&mut self) -> RemoteResult<PathBuf> {
+        self.run(|fs| fs.pwd())
+    }
+
+    fn change_dir(&mut self, dir: &Path) -> RemoteResult<PathBuf> {
+        self.run(|fs| fs.change_dir(dir))
+    }
+
+    fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>> {
+        self.run(|fs| fs.list_dir(path))
+    }
+
+    fn stat(&mut self, path: &Path) -> RemoteResult<File> {
+        self.run(|fs| fs.stat(path))
+    }
+
+    fn stat_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<File>> {
+        self.run_many(paths.len(), |fs| fs.stat_many(paths))
+    }
+
+    fn setstat(&mut self, path: &Path, metadata: Metadata) -> RemoteResult<()> {
+        self.run(|fs| fs.setstat(path, metadata))
+    }
+
+    fn exists(&mut self, path: &Path) -> RemoteResult<bool> {
+        self.run(|fs| fs.exists(path))
+    }
+
+    fn exists_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<bool>> {
+        self.run_many(paths.len(), |fs| fs.exists_many(paths))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> RemoteResult<()> {
+        self.run(|fs| fs.remove_file(path))
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> RemoteResult<()> {
+        self.run(|fs| fs.remove_dir(path))
+    }
+
+    fn create_dir(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()> {
+        self.run(|fs| fs.create_dir(path, mode))
+    }
+
+    fn read_link(&mut self, path: &Path) -> RemoteResult<PathBuf> {
+        self.run(|fs| fs.read_link(path))
+    }
+
+    fn read_links(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<PathBuf>> {
+        self.run_many(paths.len(), |fs| fs.read_links(paths))
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> RemoteResult<()> {
+        self.run(|fs| fs.symlink(path, target))
+    }
+
+    fn copy(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.run(|fs| fs.copy(src, dest))
+    }
+
+    fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.run(|fs| fs.mov(src, dest))
+    }
+
+    fn exec(&mut self, cmd: &str) -> RemoteResult<(u32, String)> {
+        self.run(|fs| fs.exec(cmd))
+    }
+
+    fn exec_with_stdin(
+        &mut self,
+        cmd: &str,
+        stdin: Box<dyn Read + Send>,
+    ) -> RemoteResult<ExecOutput> {
+        let stdin = Box::new(self.cancellable(stdin));
+        self.run(|fs| fs.exec_with_stdin(cmd, stdin))
+    }
+
+    fn append(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        let stream = self.run(|fs| fs.append(path, metadata))?;
+        Ok(stream.wrap(|stream| self.cancellable(stream)))
+    }
+
+    fn create(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        let stream = self.run(|fs| fs.create(path, metadata))?;
+        Ok(stream.wrap(|stream| self.cancellable(stream)))
+    }
+
+    fn open(&mut self, path: &Path) -> RemoteResult<ReadStream> {
+        let stream = self.run(|fs| fs.open(path))?;
+        Ok(stream.wrap(|stream| self.cancellable(stream)))
+    }
+
+    fn open_range(
+        &mut self,
+        path: &Path,
+        offset: u64,
+        len: Option<u64>,
+    ) -> RemoteResult<ReadStream> {
+        let stream = self.run(|fs| fs.open_range(path, offset, len))?;
+        Ok(stream.wrap(|stream| self.cancellable(stream)))
+    }
+
+    fn open_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<ReadStream> {
+        let stream = self.run(|fs| fs.open_named_stream(path, name))?;
+        Ok(stream.wrap(|stream| self.cancellable(stream)))
+    }
+
+    fn create_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<WriteStream> {
+        let stream = self.run(|fs| fs.create_named_stream(path, name))?;
+        Ok(stream.wrap(|stream| self.cancellable(stream)))
+    }
+
+    fn on_written(&mut self, writable: WriteStream) -> RemoteResult<()> {
+        self.inner.on_written(writable)
+    }
+
+    fn on_read(&mut self, readable: ReadStream) -> RemoteResult<()> {
+        self.inner.on_read(readable)
+    }
+
+    fn buffer_size(&self) -> usize {
+        self.inner.buffer_size()
+    }
+
+    fn last_working_dir(&self) -> Option<PathBuf> {
+        self.inner.last_working_dir()
+    }
+
+    fn fsync(&mut self, path: &Path) -> RemoteResult<()> {
+        self.run(|fs| fs.fsync(path))
+    }
+
+    fn append_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        let reader = Box::new(self.cancellable(reader));
+        self.run(|fs| fs.append_file(path, metadata, reader))
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        let reader = Box::new(self.cancellable(reader));
+        self.run(|fs| fs.create_file(path, metadata, reader))
+    }
+
+    fn open_file(&mut self, src: &Path) -> RemoteResult<(u64, Vec<u8>)> {
+        let mut stream = match self.open(src) {
+            Ok(stream) => stream,
+            Err(err) if err.kind == RemoteErrorType::UnsupportedFeature => {
+                return self.run(|fs| fs.open_file(src));
+            }
+            Err(err) => return Err(err),
+        };
+        let mut dest = vec![];
+        let sz = io::copy(&mut stream, &mut dest).map_err(|e| {
+            self.cancelled_or(RemoteError::new_ex(
+                RemoteErrorType::ProtocolError,
+                e.to_string(),
+            ))
+        })?;
+        self.on_read(stream)?;
+        Ok((sz, dest))
+    }
+
+    fn checksum(&mut self, path: &Path) -> RemoteResult<String> {
+        self.run(|fs| fs.checksum(path))
+    }
+}
+
+/// A stream which fails once the token is cancelled
+struct Cancellable<S> {
+    stream: S,
+    token: CancellationToken,
+}
+
+impl<S> Cancellable<S> {
+    fn check(&self) -> io::Result<()> {
+        match self.token.is_cancelled() {
+            true => Err(io::Error::other(RemoteErrorType::Cancelled)),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<S: Read> Read for Cancellable<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        self.stream.read(buf)
+    }
+}
+
+impl<S: Write> Write for Cancellable<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check()?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S: Seek> Seek for Cancellable<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.stream.seek(pos)
+    }
+}
+
+impl ReadAndSeek for Cancellable<ReadStream> {}
+
+impl WriteAndSeek for Cancellable<WriteStream> {}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mock::MemoryFs;
+
+    /// A reader producing an endless stream of zeros, cancelling `token` after `chunks` reads
+    struct SlowReader {
+        chunks: usize,
+        token: CancellationToken,
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.chunks.checked_sub(1) {
+                Some(chunks) => self.chunks = chunks,
+                None => self.token.cancel(),
+            }
+            buf.fill(0);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn should_cancel_transfer() {
+        let token = CancellationToken::new();
+        let mut client = CancellableFs::new(MemoryFs::new(), token.clone());
+        let reader = SlowReader {
+            chunks: 4,
+            token: token.clone(),
+        };
+        let err = client
+            .create_file(Path::new("/a.bin"), &Metadata::default(), Box::new(reader))
+            .unwrap_err();
+        assert_eq!(err.kind, RemoteErrorType::Cancelled);
+        // operations are cancelled until the token is reset
+        assert_eq!(
+            client.stat(Path::new("/a.bin")).unwrap_err().kind,
+            RemoteErrorType::Cancelled
+        );
+        token.reset();
+        assert!(client.stat(Path::new("/a.bin")).is_ok());
+    }
+
+    #[test]
+    fn should_cancel_stream_between_chunks() {
+        let token = CancellationToken::new();
+        let mut client = CancellableFs::new(
+            MemoryFs::new().with_file("/a.txt", b"hello world"),
+            token.clone(),
+        );
+        let mut stream = client.open(Path::new("/a.txt")).unwrap();
+        assert!(stream.seekable());
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        token.cancel();
+        assert!(stream.read(&mut buf).is_err());
+        client.on_read(stream).unwrap();
+        token.reset();
+        assert_eq!(
+            client.open_file(Path::new("/a.txt")).unwrap().1,
+            b"hello world"
+        );
+        token.cancel();
+        assert_eq!(
+            client.open_file(Path::new("/a.txt")).unwrap_err().kind,
+            RemoteErrorType::Cancelled
+        );
+    }
+}
@@ -4,6 +4,8 @@
 
 use std::error::Error as StdError;
 use std::fmt;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::path::Path;
 
 use thiserror::Error;
 
@@ -34,6 +36,10 @@ pub enum RemoteErrorType {
     StatFailed,
     #[error("bad file")]
     BadFile,
+    #[error("operation cancelled")]
+    Cancelled,
+    #[error("checksum mismatch")]
+    ChecksumMismatch,
     #[error("directory already exists")]
     DirectoryAlreadyExists,
     #[error("directory is not empty")]
@@ -44,6 +50,12 @@ pub enum RemoteErrorType {
     CouldNotOpenFile,
     #[error("failed to remove file")]
     CouldNotRemoveFile,
+    #[error("cross-device operation")]
+    CrossDevice,
+    #[error("host key mismatch")]
+    HostKeyMismatch,
+    #[error("incomplete transfer")]
+    IncompleteTransfer,
     #[error("IO error")]
     IoError,
     #[error("no such file or directory")]
@@ -70,6 +82,59 @@ impl RemoteError {
         err.msg = Some(msg.to_string());
         err
     }
+
+    /// Instantiates a new [`RemoteErrorType::NoSuchFileOrDirectory`] error for `path`
+    pub fn not_found<P: AsRef<Path>>(path: P) -> RemoteError {
+        RemoteError::new_ex(
+            RemoteErrorType::NoSuchFileOrDirectory,
+            path.as_ref().display(),
+        )
+    }
+
+    /// Instantiates a new [`RemoteErrorType::UnsupportedFeature`] error for `feature`
+    pub fn unsupported<S: ToString>(feature: S) -> RemoteError {
+        RemoteError::new_ex(RemoteErrorType::UnsupportedFeature, feature)
+    }
+
+    /// Instantiates a new RemoteError from an IO error.
+    ///
+    /// Not found and permission denied errors are converted into [`RemoteErrorType::NoSuchFileOrDirectory`]
+    /// and [`RemoteErrorType::PexError`]; any other error is a [`RemoteErrorType::IoError`]
+    pub fn io(err: IoError) -> RemoteError {
+        let kind = match err.kind() {
+            IoErrorKind::NotFound => RemoteErrorType::NoSuchFileOrDirectory,
+            IoErrorKind::PermissionDenied => RemoteErrorType::PexError,
+            _ => RemoteErrorType::IoError,
+        };
+        RemoteError::new_ex(kind, err)
+    }
+}
+
+/// Construct a [`RemoteError`] of the provided kind, with an optional formatted message.
+///
+/// ```
+/// use remotefs::{remote_err, RemoteErrorType};
+///
+/// let err = remote_err!(RemoteErrorType::BadFile, "{} is not a regular file", "/tmp");
+/// assert_eq!(err.to_string(), "bad file (/tmp is not a regular file)");
+/// ```
+#[macro_export]
+macro_rules! remote_err {
+    ($kind:expr) => {
+        $crate::RemoteError::new($kind)
+    };
+    ($kind:expr, $($arg:tt)+) => {
+        $crate::RemoteError::new_ex($kind, format!($($arg)+))
+    };
+}
+
+/// Return early with a [`RemoteError`] of the provided kind, with an optional formatted message.
+/// Arguments are the same of [`remote_err!`].
+#[macro_export]
+macro_rules! remote_bail {
+    ($($arg:tt)+) => {
+        return Err($crate::remote_err!($($arg)+))
+    };
 }
 
 impl fmt::Display for RemoteError {
@@ -119,6 +184,26 @@ mod test {
             format!("{}", RemoteError::new(RemoteErrorType::BadAddress)),
             String::from("bad address syntax")
         );
+        assert_eq!(
+            format!("{}", RemoteError::new(RemoteErrorType::Cancelled)),
+            String::from("operation cancelled")
+        );
+        assert_eq!(
+            format!("{}", RemoteError::new(RemoteErrorType::IncompleteTransfer)),
+            String::from("incomplete transfer")
+        );
+        assert_eq!(
+            format!("{}", RemoteError::new(RemoteErrorType::HostKeyMismatch)),
+            String::from("host key mismatch")
+        );
+        assert_eq!(
+            format!("{}", RemoteError::new(RemoteErrorType::ChecksumMismatch)),
+            String::from("checksum mismatch")
+        );
+        assert_eq!(
+            format!("{}", RemoteError::new(RemoteErrorType::CrossDevice)),
+            String::from("cross-device operation")
+        );
         assert_eq!(
             format!("{}", RemoteError::new(RemoteErrorType::ConnectionError)),
             String::from("connection error")
@@ -167,4 +252,37 @@ mod test {
         let error = RemoteError::new(RemoteErrorType::UnsupportedFeature);
         assert!(error.source().is_some());
     }
+
+    #[test]
+    fn should_construct_errors_with_helpers() {
+        let err = RemoteError::not_found("/tmp/a.txt");
+        assert_eq!(err.kind, RemoteErrorType::NoSuchFileOrDirectory);
+        assert_eq!(err.to_string(), "no such file or directory (/tmp/a.txt)");
+        let err = RemoteError::unsupported("symlink");
+        assert_eq!(err.kind, RemoteErrorType::UnsupportedFeature);
+        assert_eq!(err.to_string(), "unsupported feature (symlink)");
+        let err = RemoteError::io(IoError::new(IoErrorKind::NotFound, "gone"));
+        assert_eq!(err.kind, RemoteErrorType::NoSuchFileOrDirectory);
+        assert_eq!(err.msg.as_deref(), Some("gone"));
+        let err = RemoteError::io(IoError::new(IoErrorKind::PermissionDenied, "denied"));
+        assert_eq!(err.kind, RemoteErrorType::PexError);
+        let err = RemoteError::io(IoError::new(IoErrorKind::UnexpectedEof, "eof"));
+        assert_eq!(err.kind, RemoteErrorType::IoError);
+    }
+
+    #[test]
+    fn should_construct_errors_with_macros() {
+        let err = remote_err!(RemoteErrorType::StatFailed);
+        assert_eq!(err, RemoteError::new(RemoteErrorType::StatFailed));
+        let err = remote_err!(RemoteErrorType::BadFile, "{} is a {}", "/tmp", "directory");
+        assert_eq!(err.kind, RemoteErrorType::BadFile);
+        assert_eq!(err.msg.as_deref(), Some("/tmp is a directory"));
+        fn bail() -> RemoteResult<()> {
+            remote_bail!(RemoteErrorType::ProtocolError, "unexpected reply {}", 500);
+        }
+        assert_eq!(
+            bail().unwrap_err().to_string(),
+            "protocol error (unexpected reply 500)"
+        );
+    }
 }
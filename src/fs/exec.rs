@@ -0,0 +1,43 @@
+//! ## Exec
+//!
+//! output of commands executed on the remote host
+
+/// Output of a command executed with [`crate::RemoteFs::exec_with_stdin`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExecOutput {
+    /// Exit code of the command
+    pub exit_code: u32,
+    /// Output of the command (stdout)
+    pub stdout: String,
+    /// Amount of bytes written to the stdin of the command.
+    /// It's less than the size of the input if the command closed its stdin early
+    pub stdin_written: u64,
+}
+
+impl ExecOutput {
+    /// Returns whether the command exited with code `0`
+    pub fn is_success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_create_exec_output() {
+        let output = ExecOutput::default();
+        assert!(output.is_success());
+        assert_eq!(output.stdin_written, 0);
+        let output = ExecOutput {
+            exit_code: 1,
+            stdout: String::from("error"),
+            stdin_written: 12,
+        };
+        assert!(!output.is_success());
+    }
+}
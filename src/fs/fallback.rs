@@ -0,0 +1,522 @@
+//! ## Fallback
+//!
+//! a `RemoteFs` wrapper which falls back to other operations when an operation is not supported
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::sync::{copy_buffered, move_by_copy};
+use super::{
+    ExecOutput, File, HostKeyVerifier, Metadata, ReadStream, RemoteError, RemoteErrorType,
+    RemoteFs, RemoteResult, UnixPex, Welcome, WriteStream,
+};
+use crate::utils::digest::Sha256;
+use crate::utils::path::absolutize;
+
+/// What [`FallbackFs`] does when an operation returns [`RemoteErrorType::UnsupportedFeature`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fallback {
+    /// Return the error
+    Fail,
+    /// Return success without doing anything.
+    /// Operations which return a value can't be skipped, so they fail instead
+    Skip,
+    /// Perform the operation with other operations, such as copying a file by downloading and uploading it.
+    /// Operations which can't be emulated fail instead
+    Emulate,
+}
+
+/// A [`RemoteFs`] wrapper which applies a [`Fallback`] when an operation is not supported by the inner file system,
+/// so that the same code can target servers with different capabilities.
+///
+/// The fallback can be configured for each of these operations; all the other operations are forwarded as they are:
+///
+/// - [`RemoteFs::copy`]: emulated by streaming each file from the source to the destination.
+///   Defaults to [`Fallback::Emulate`]
+/// - [`RemoteFs::mov`]: emulated by copying the file, as [`RemoteFs::copy`] does, applying the metadata of the source
+///   and removing the source, as [`RemoteFs::move_with_fallback`] does. Defaults to [`Fallback::Emulate`]
+/// - [`RemoteFs::setstat`]: can't be emulated. Defaults to [`Fallback::Skip`]
+/// - [`RemoteFs::symlink`]: can't be emulated. Defaults to [`Fallback::Fail`]
+/// - [`RemoteFs::checksum`]: emulated by downloading the file and hashing it. Defaults to [`Fallback::Emulate`]
+pub struct FallbackFs<T: RemoteFs> {
+    inner: T,
+    copy: Fallback,
+    mov: Fallback,
+    setstat: Fallback,
+    symlink: Fallback,
+    checksum: Fallback,
+}
+
+impl<T: RemoteFs> FallbackFs<T> {
+    /// Wrap `inner` into a new `FallbackFs` with the default fallbacks
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            copy: Fallback::Emulate,
+            mov: Fallback::Emulate,
+            setstat: Fallback::Skip,
+            symlink: Fallback::Fail,
+            checksum: Fallback::Emulate,
+        }
+    }
+
+    /// Construct `FallbackFs` with the fallback for [`RemoteFs::copy`]
+    pub fn copy_fallback(mut self, fallback: Fallback) -> Self {
+        self.copy = fallback;
+        self
+    }
+
+    /// Construct `FallbackFs` with the fallback for [`RemoteFs::mov`]
+    pub fn mov_fallback(mut self, fallback: Fallback) -> Self {
+        self.mov = fallback;
+        self
+    }
+
+    /// Construct `FallbackFs` with the fallback for [`RemoteFs::setstat`]
+    pub fn setstat_fallback(mut self, fallback: Fallback) -> Self {
+        self.setstat = fallback;
+        self
+    }
+
+    /// Construct `FallbackFs` with the fallback for [`RemoteFs::symlink`]
+    pub fn symlink_fallback(mut self, fallback: Fallback) -> Self {
+        self.symlink = fallback;
+        self
+    }
+
+    /// Construct `FallbackFs` with the fallback for [`RemoteFs::checksum`]
+    pub fn checksum_fallback(mut self, fallback: Fallback) -> Self {
+        self.checksum = fallback;
+        self
+    }
+
+    /// Get a reference to the inner file system
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner file system
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner file system
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Copy `src` to `dest` downloading and uploading each file
+    fn copy_by_transfer(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        let entry = self.inner.stat(src)?;
+        if entry.is_dir() {
+            let mode = entry.metadata.mode.unwrap_or(UnixPex::DIR);
+            self.inner.create_dir(dest, mode)?;
+            for child in self.inner.list_dir(src)? {
+                self.copy_by_transfer(child.path(), dest.join(child.name()).as_path())?;
+            }
+            return Ok(());
+        }
+        if let Some(target) = entry.metadata.symlink.as_deref() {
+            return self.inner.symlink(dest, target);
+        }
+        trace!(
+            "copying {} to {} by transfer",
+            src.display(),
+            dest.display()
+        );
+        let mut reader = self.inner.open(src)?;
+        let mut writer = self.inner.create(dest, &entry.metadata)?;
+        copy_buffered(&mut reader, &mut writer, self.inner.buffer_size())
+            .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+        self.inner.on_read(reader)?;
+        self.inner.on_written(writer)
+    }
+
+    /// Compute the checksum of `path` downloading it
+    fn checksum_by_transfer(&mut self, path: &Path) -> RemoteResult<String> {
+        let mut stream = self.inner.open(path)?;
+        let mut hasher = Sha256::default();
+        let mut buffer = vec![0; self.inner.buffer_size().max(1)];
+        loop {
+            let read = stream
+                .read(&mut buffer)
+                .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        self.inner.on_read(stream)?;
+        Ok(hasher.finalize())
+    }
+}
+
+/// Returns whether `result` failed because the operation is not supported
+fn is_unsupported<R>(result: &RemoteResult<R>) -> bool {
+    matches!(result, Err(err) if err.kind == RemoteErrorType::UnsupportedFeature)
+}
+
+impl<T: RemoteFs> RemoteFs for FallbackFs<T> {
+    fn connect(&mut self) -> RemoteResult<Welcome> {
+        self.inner.connect()
+    }
+
+    fn set_host_key_verifier(&mut self, verifier: HostKeyVerifier) {
+        self.inner.set_host_key_verifier(verifier)
+    }
+
+    fn disconnect(&mut self) -> RemoteResult<()> {
+        self.inner.disconnect()
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn pwd(&mut self) -> RemoteResult<PathBuf> {
+        self.inner.pwd()
+    }
+
+    fn change_dir(&mut self, dir: &Path) -> RemoteResult<PathBuf> {
+        self.inner.change_dir(dir)
+    }
+
+    fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>> {
+        self.inner.list_dir(path)
+    }
+
+    fn stat(&mut self, path: &Path) -> RemoteResult<File> {
+        self.inner.stat(path)
+    }
+
+    fn stat_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<File>> {
+        self.inner.stat_many(paths)
+    }
+
+    fn setstat(&mut self, path: &Path, metadata: Metadata) -> RemoteResult<()> {
+        let result = self.inner.setstat(path, metadata);
+        match self.setstat {
+            Fallback::Skip if is_unsupported(&result) => {
+                debug!("setstat is not supported; skipping {}", path.display());
+                Ok(())
+            }
+            _ => result,
+        }
+    }
+
+    fn exists(&mut self, path: &Path) -> RemoteResult<bool> {
+        self.inner.exists(path)
+    }
+
+    fn exists_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<bool>> {
+        self.inner.exists_many(paths)
+    }
+
+    fn remove_file(&mut self, path: &Path) -> RemoteResult<()> {
+        self.inner.remove_file(path)
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> RemoteResult<()> {
+        self.inner.remove_dir(path)
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> RemoteResult<()> {
+        self.inner.remove_dir_all(path)
+    }
+
+    fn create_dir(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()> {
+        self.inner.create_dir(path, mode)
+    }
+
+    fn read_link(&mut self, path: &Path) -> RemoteResult<PathBuf> {
+        self.inner.read_link(path)
+    }
+
+    fn read_links(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<PathBuf>> {
+        self.inner.read_links(paths)
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> RemoteResult<()> {
+        let result = self.inner.symlink(path, target);
+        match self.symlink {
+            Fallback::Skip if is_unsupported(&result) => {
+                debug!("symlink is not supported; skipping {}", path.display());
+                Ok(())
+            }
+            _ => result,
+        }
+    }
+
+    fn copy(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        let result = self.inner.copy(src, dest);
+        if !is_unsupported(&result) {
+            return result;
+        }
+        match self.copy {
+            Fallback::Fail => result,
+            Fallback::Skip => {
+                debug!("copy is not supported; skipping {}", src.display());
+                Ok(())
+            }
+            Fallback::Emulate => {
+                debug!("copy is not supported; transferring {}", src.display());
+                self.copy_by_transfer(src, dest)
+            }
+        }
+    }
+
+    fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        let result = self.inner.mov(src, dest);
+        if !is_unsupported(&result) {
+            return result;
+        }
+        match self.mov {
+            Fallback::Fail => result,
+            Fallback::Skip => {
+                debug!("mov is not supported; skipping {}", src.display());
+                Ok(())
+            }
+            Fallback::Emulate => {
+                debug!("mov is not supported; copying {}", src.display());
+                let wrkdir = self.pwd()?;
+                let src = absolutize(&wrkdir, src);
+                let dest = absolutize(&wrkdir, dest);
+                move_by_copy(self, src.as_path(), dest.as_path())
+            }
+        }
+    }
+
+    fn exec(&mut self, cmd: &str) -> RemoteResult<(u32, String)> {
+        self.inner.exec(cmd)
+    }
+
+    fn exec_with_stdin(
+        &mut self,
+        cmd: &str,
+        stdin: Box<dyn Read + Send>,
+    ) -> RemoteResult<ExecOutput> {
+        self.inner.exec_with_stdin(cmd, stdin)
+    }
+
+    fn append(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.inner.append(path, metadata)
+    }
+
+    fn create(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.inner.create(path, metadata)
+    }
+
+    fn open(&mut self, path: &Path) -> RemoteResult<ReadStream> {
+        self.inner.open(path)
+    }
+
+    fn open_range(
+        &mut self,
+        path: &Path,
+        offset: u64,
+        len: Option<u64>,
+    ) -> RemoteResult<ReadStream> {
+        self.inner.open_range(path, offset, len)
+    }
+
+    fn open_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<ReadStream> {
+        self.inner.open_named_stream(path, name)
+    }
+
+    fn create_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<WriteStream> {
+        self.inner.create_named_stream(path, name)
+    }
+
+    fn on_written(&mut self, writable: WriteStream) -> RemoteResult<()> {
+        self.inner.on_written(writable)
+    }
+
+    fn on_read(&mut self, readable: ReadStream) -> RemoteResult<()> {
+        self.inner.on_read(readable)
+    }
+
+    fn buffer_size(&self) -> usize {
+        self.inner.buffer_size()
+    }
+
+    fn last_working_dir(&self) -> Option<PathBuf> {
+        self.inner.last_working_dir()
+    }
+
+    fn fsync(&mut self, path: &Path) -> RemoteResult<()> {
+        self.inner.fsync(path)
+    }
+
+    fn append_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        self.inner.append_file(path, metadata, reader)
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        self.inner.create_file(path, metadata, reader)
+    }
+
+    fn open_file(&mut self, src: &Path) -> RemoteResult<(u64, Vec<u8>)> {
+        self.inner.open_file(src)
+    }
+
+    fn checksum(&mut self, path: &Path) -> RemoteResult<String> {
+        let result = self.inner.checksum(path);
+        match self.checksum {
+            Fallback::Emulate if is_unsupported(&result) => {
+                debug!("checksum is not supported; downloading {}", path.display());
+                self.checksum_by_transfer(path)
+            }
+            _ => result,
+        }
+    }
+
+    #[cfg(feature = "find")]
+    fn find(&mut self, search: &str) -> RemoteResult<Vec<File>> {
+        self.inner.find(search)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mock::MemoryFs;
+
+    #[test]
+    fn should_copy_by_transfer_when_copy_is_unsupported() {
+        let mut client = FallbackFs::new(
+            MemoryFs::new()
+                .with_dir("/a")
+                .with_file("/a/b.txt", b"hello")
+                .with_dir("/a/c")
+                .with_file("/a/c/d.txt", b"world")
+                .with_symlink("/a/e", "b.txt")
+                .without_copy(),
+        );
+        assert_eq!(
+            client
+                .inner_mut()
+                .copy(Path::new("/a"), Path::new("/f"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        client.copy(Path::new("/a"), Path::new("/f")).unwrap();
+        let client = client.into_inner();
+        assert_eq!(client.content("/f/b.txt").unwrap(), b"hello");
+        assert_eq!(client.content("/f/c/d.txt").unwrap(), b"world");
+        assert_eq!(client.content("/a/b.txt").unwrap(), b"hello");
+        let mut client = FallbackFs::new(client);
+        assert_eq!(
+            client.stat(Path::new("/f/e")).unwrap().metadata.symlink,
+            Some(PathBuf::from("b.txt"))
+        );
+    }
+
+    #[test]
+    fn should_move_by_transfer_when_copy_and_mov_are_unsupported() {
+        let mut client = FallbackFs::new(
+            MemoryFs::new()
+                .with_file("/a.txt", b"hello")
+                .without_copy()
+                .without_mov(),
+        );
+        client
+            .mov(Path::new("/a.txt"), Path::new("/b.txt"))
+            .unwrap();
+        assert!(!client.exists(Path::new("/a.txt")).unwrap());
+        assert_eq!(client.into_inner().content("/b.txt").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn should_preserve_metadata_when_moving_by_transfer() {
+        let modified = UNIX_EPOCH + Duration::from_secs(86400);
+        let mut client = FallbackFs::new(
+            MemoryFs::new()
+                .with_dir("/a")
+                .with_file("/a/b.txt", b"hello")
+                .without_copy()
+                .without_mov(),
+        );
+        client
+            .setstat(
+                Path::new("/a/b.txt"),
+                Metadata::default()
+                    .mode(UnixPex::from(0o600))
+                    .modified(modified),
+            )
+            .unwrap();
+        client.mov(Path::new("/a"), Path::new("/c")).unwrap();
+        assert!(!client.exists(Path::new("/a")).unwrap());
+        let file = client.stat(Path::new("/c/b.txt")).unwrap();
+        assert_eq!(file.metadata().mode, Some(UnixPex::from(0o600)));
+        assert_eq!(file.metadata().modified, Some(modified));
+    }
+
+    #[test]
+    fn should_remove_copy_if_moving_by_transfer_fails() {
+        let mut client = FallbackFs::new(
+            MemoryFs::new()
+                .with_dir("/a")
+                .with_file("/a/b.txt", b"hello")
+                .without_copy()
+                .without_mov()
+                .without_setstat(),
+        )
+        .setstat_fallback(Fallback::Fail);
+        assert_eq!(
+            client
+                .mov(Path::new("/a"), Path::new("/c"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        assert!(!client.exists(Path::new("/c")).unwrap());
+        assert_eq!(client.into_inner().content("/a/b.txt").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn should_apply_configured_fallbacks() {
+        let mut client =
+            FallbackFs::new(MemoryFs::new().with_file("/a.txt", b"hello").without_copy())
+                .copy_fallback(Fallback::Fail);
+        assert_eq!(
+            client
+                .copy(Path::new("/a.txt"), Path::new("/b.txt"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        let mut client = client.copy_fallback(Fallback::Skip);
+        assert!(client
+            .copy(Path::new("/a.txt"), Path::new("/b.txt"))
+            .is_ok());
+        assert!(!client.exists(Path::new("/b.txt")).unwrap());
+        // checksum
+        let mut hasher = Sha256::default();
+        hasher.update(b"hello");
+        assert_eq!(
+            client.checksum(Path::new("/a.txt")).unwrap(),
+            hasher.finalize()
+        );
+        let mut client = client.checksum_fallback(Fallback::Skip);
+        assert_eq!(
+            client.checksum(Path::new("/a.txt")).unwrap_err().kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+    }
+}
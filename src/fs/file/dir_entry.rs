@@ -0,0 +1,122 @@
+//! ## Dir entry
+//!
+//! a minimal directory entry shape, to filter remote entries by path and kind
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use super::{File, FileType};
+
+/// A minimal, borrowed view of a [`File`], exposing its path, file name and file type.
+///
+/// Filters which only need the path and whether the entry is a directory
+/// (e.g. `filter(entry.path(), entry.is_dir())`) can take a `DirEntryLike` instead of a whole [`File`].
+/// No compatibility with the entry types of local walkers (such as the `ignore` or `walkdir` crates) is implied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DirEntryLike<'a> {
+    path: &'a Path,
+    file_type: FileType,
+}
+
+impl<'a> DirEntryLike<'a> {
+    /// Get entry path
+    pub fn path(&self) -> &'a Path {
+        self.path
+    }
+
+    /// Get entry file name. The file name of the root directory is empty
+    pub fn file_name(&self) -> &'a OsStr {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    /// Get entry file type
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns whether the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.file_type.is_dir()
+    }
+}
+
+impl<'a> From<&'a File> for DirEntryLike<'a> {
+    fn from(file: &'a File) -> Self {
+        Self {
+            path: file.path(),
+            file_type: file.metadata().file_type,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::fs::Metadata;
+
+    /// A matcher supporting `*.ext` patterns and `name/` directory patterns
+    struct PatternMatcher(Vec<&'static str>);
+
+    impl PatternMatcher {
+        fn matched(&self, path: &Path, is_dir: bool) -> bool {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            self.0
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('/') {
+                    Some(dir) => is_dir && name == dir,
+                    None => match pattern.strip_prefix('*') {
+                        Some(ext) => name.ends_with(ext),
+                        None => name == *pattern,
+                    },
+                })
+        }
+    }
+
+    fn entry(path: &str, file_type: FileType) -> File {
+        File {
+            path: PathBuf::from(path),
+            metadata: Metadata::default().file_type(file_type),
+        }
+    }
+
+    #[test]
+    fn should_make_dir_entry_from_file() {
+        let file = entry("/home/omar/readme.md", FileType::File);
+        let dir_entry = DirEntryLike::from(&file);
+        assert_eq!(dir_entry.path(), Path::new("/home/omar/readme.md"));
+        assert_eq!(dir_entry.file_name(), OsStr::new("readme.md"));
+        assert_eq!(dir_entry.file_type(), FileType::File);
+        assert!(!dir_entry.is_dir());
+    }
+
+    #[test]
+    fn should_filter_remote_entries_by_path_and_kind() {
+        let matcher = PatternMatcher(vec!["target/", "*.log"]);
+        let entries = [
+            entry("/project/src", FileType::Directory),
+            entry("/project/target", FileType::Directory),
+            entry("/project/build.log", FileType::File),
+            entry("/project/target.txt", FileType::File),
+            entry("/project/Cargo.toml", FileType::File),
+        ];
+        let kept: Vec<&Path> = entries
+            .iter()
+            .map(DirEntryLike::from)
+            .filter(|entry| !matcher.matched(entry.path(), entry.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        assert_eq!(
+            kept,
+            vec![
+                Path::new("/project/src"),
+                Path::new("/project/target.txt"),
+                Path::new("/project/Cargo.toml"),
+            ]
+        );
+    }
+}
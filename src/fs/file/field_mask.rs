@@ -0,0 +1,88 @@
+//! ## Field mask
+//!
+//! a set of file fields to compare
+
+use std::ops::{BitOr, BitOrAssign, Not};
+
+/// A set of fields of a [`super::File`], compared by [`super::File::eq_masked`].
+///
+/// Masks are combined with `|`, such as `FieldMask::SIZE | FieldMask::MTIME`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldMask(u8);
+
+impl FieldMask {
+    /// No field
+    pub const NONE: Self = Self(0);
+    /// Normalized path (see [`super::File::path_eq`])
+    pub const PATH: Self = Self(1);
+    /// File type
+    pub const FILE_TYPE: Self = Self(1 << 1);
+    /// Size
+    pub const SIZE: Self = Self(1 << 2);
+    /// Modify time
+    pub const MTIME: Self = Self(1 << 3);
+    /// Unix permissions
+    pub const MODE: Self = Self(1 << 4);
+    /// User and group ids
+    pub const OWNER: Self = Self(1 << 5);
+    /// All the fields
+    pub const ALL: Self = Self(0b11_1111);
+
+    /// Returns whether all the fields in `other` are in `self`
+    pub fn contains(&self, other: FieldMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for FieldMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl BitOr for FieldMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for FieldMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Not for FieldMask {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0 & Self::ALL.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_combine_field_masks() {
+        let mask = FieldMask::SIZE | FieldMask::MTIME;
+        assert!(mask.contains(FieldMask::SIZE));
+        assert!(mask.contains(FieldMask::MTIME));
+        assert!(!mask.contains(FieldMask::MODE));
+        assert!(!mask.contains(FieldMask::SIZE | FieldMask::MODE));
+        assert!(FieldMask::ALL.contains(mask));
+        assert!(mask.contains(FieldMask::NONE));
+        assert_eq!(FieldMask::default(), FieldMask::ALL);
+        assert_eq!(!FieldMask::ALL, FieldMask::NONE);
+        assert!(!(!FieldMask::SIZE).contains(FieldMask::SIZE));
+        let mut mask = FieldMask::NONE;
+        mask |= FieldMask::OWNER;
+        assert_eq!(mask, FieldMask::OWNER);
+    }
+}
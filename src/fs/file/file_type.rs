@@ -5,23 +5,18 @@
 use std::fs::FileType as StdFileType;
 
 /// Describes the file type (directory, regular file or symlink)
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum FileType {
     /// A directory
     Directory,
     /// Regular file
+    #[default]
     File,
     /// Symbolic link. If the file is a symlink pointing to a directory,
     /// this will be still considered a Symlink.
     Symlink,
 }
 
-impl Default for FileType {
-    fn default() -> Self {
-        Self::File
-    }
-}
-
 impl FileType {
     /// Returns whether file is a directory
     pub fn is_dir(&self) -> bool {
@@ -6,25 +6,34 @@ use std::fs::Metadata as StdMetadata;
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::{FileType, UnixPex};
 
 /// File metadata
+///
+/// Fields set to `None` are unknown, since not every protocol reports every attribute.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Metadata {
     /// Last access time
     pub accessed: Option<SystemTime>,
+    /// If file is a directory, the amount of entries it contains, when the protocol reports it without listing the directory
+    pub child_count: Option<u64>,
     /// Creation time
     pub created: Option<SystemTime>,
+    /// Id of the device containing the file
+    pub dev: Option<u64>,
     /// Group id
     pub gid: Option<u32>,
     /// Unix permissions
     pub mode: Option<UnixPex>,
     /// Modify time
     pub modified: Option<SystemTime>,
-    /// File size in bytes
+    /// File size in bytes. `0` if the file is empty, or if its size is unknown (e.g. for directories on some protocols)
     pub size: u64,
+    /// Names of the alternate data streams (or resource forks) of the file, if the protocol exposes them.
+    /// Use [`crate::RemoteFs::open_named_stream`] to read a stream
+    pub streams: Option<Vec<String>>,
     /// If file is symlink, contains the path of the file it is pointing to
     pub symlink: Option<PathBuf>,
     /// File type
@@ -33,15 +42,20 @@ pub struct Metadata {
     pub uid: Option<u32>,
 }
 
+/// The default metadata describes a regular file whose attributes are all unknown:
+/// every optional field is `None` and the size is `0`
 impl Default for Metadata {
     fn default() -> Self {
         Self {
             accessed: None,
+            child_count: None,
             created: None,
+            dev: None,
             gid: None,
             mode: None,
             modified: None,
             size: 0,
+            streams: None,
             symlink: None,
             file_type: FileType::File,
             uid: None,
@@ -56,12 +70,24 @@ impl Metadata {
         self
     }
 
+    /// Construct metadata with the amount of entries in the directory
+    pub fn child_count(mut self, child_count: u64) -> Self {
+        self.child_count = Some(child_count);
+        self
+    }
+
     /// Construct metadata with created
     pub fn created(mut self, created: SystemTime) -> Self {
         self.created = Some(created);
         self
     }
 
+    /// Construct metadata with device id
+    pub fn dev(mut self, dev: u64) -> Self {
+        self.dev = Some(dev);
+        self
+    }
+
     /// Construct metadata with group id
     pub fn gid(mut self, gid: u32) -> Self {
         self.gid = Some(gid);
@@ -86,6 +112,12 @@ impl Metadata {
         self
     }
 
+    /// Construct metadata with the names of the alternate data streams
+    pub fn streams(mut self, streams: Vec<String>) -> Self {
+        self.streams = Some(streams);
+        self
+    }
+
     /// Construct metadata with symlink
     pub fn symlink<P: AsRef<Path>>(mut self, p: P) -> Self {
         self.symlink = Some(p.as_ref().to_path_buf());
@@ -119,10 +151,67 @@ impl Metadata {
         self.file_type.is_symlink()
     }
 
+    /// Returns whether the setuid bit is set in the mode. Returns `false` if the mode is unknown
+    pub fn is_setuid(&self) -> bool {
+        self.mode.map(|mode| mode.is_setuid()).unwrap_or(false)
+    }
+
+    /// Returns whether the setgid bit is set in the mode. Returns `false` if the mode is unknown
+    pub fn is_setgid(&self) -> bool {
+        self.mode.map(|mode| mode.is_setgid()).unwrap_or(false)
+    }
+
+    /// Returns whether the sticky bit is set in the mode. Returns `false` if the mode is unknown
+    pub fn is_sticky(&self) -> bool {
+        self.mode.map(|mode| mode.is_sticky()).unwrap_or(false)
+    }
+
     /// Set symlink
     pub fn set_symlink<P: AsRef<Path>>(&mut self, p: P) {
         self.symlink = Some(p.as_ref().to_path_buf());
     }
+
+    /// Returns a copy of the metadata, with access, creation and modify times rounded down to `precision`,
+    /// such as seconds with `Duration::from_secs(1)`.
+    /// Useful to get stable values to hash or compare, regardless of the sub-second jitter of times across stats.
+    /// Times before the UNIX epoch and a `precision` of zero leave times unchanged
+    pub fn truncated(&self, precision: Duration) -> Metadata {
+        let truncate = |time: Option<SystemTime>| {
+            time.map(|time| {
+                let precision = precision.as_nanos();
+                match time.duration_since(UNIX_EPOCH) {
+                    Ok(elapsed) if precision > 0 => {
+                        let nanos = elapsed.as_nanos() % precision;
+                        time - Duration::from_nanos(nanos as u64)
+                    }
+                    _ => time,
+                }
+            })
+        };
+        Metadata {
+            accessed: truncate(self.accessed),
+            created: truncate(self.created),
+            modified: truncate(self.modified),
+            ..self.clone()
+        }
+    }
+
+    /// Returns whether `self` and `other` describe the same content:
+    /// file type, size, modify time, permissions, symlink target and ownership.
+    /// Access and creation times are not compared.
+    pub fn content_eq(&self, other: &Metadata) -> bool {
+        self.content_eq_ignore_ownership(other) && self.uid == other.uid && self.gid == other.gid
+    }
+
+    /// Same as [`Metadata::content_eq`], but user and group ids are not compared.
+    /// Useful when syncing between servers with different user databases.
+    pub fn content_eq_ignore_ownership(&self, other: &Metadata) -> bool {
+        self.file_type == other.file_type
+            && self.size == other.size
+            && self.modified == other.modified
+            && self.mode == other.mode
+            && self.symlink == other.symlink
+    }
 }
 
 #[cfg(target_family = "windows")]
@@ -130,12 +219,15 @@ impl From<StdMetadata> for Metadata {
     fn from(metadata: StdMetadata) -> Self {
         Self {
             accessed: metadata.accessed().ok(),
+            child_count: None,
             created: metadata.created().ok(),
+            dev: None,
             gid: None,
             file_type: FileType::from(metadata.file_type()),
             modified: metadata.modified().ok(),
             mode: None,
             size: metadata.len(),
+            streams: None,
             symlink: None,
             uid: None,
         }
@@ -147,7 +239,9 @@ impl From<StdMetadata> for Metadata {
     fn from(metadata: StdMetadata) -> Self {
         Self {
             accessed: metadata.accessed().ok(),
+            child_count: None,
             created: metadata.created().ok(),
+            dev: Some(metadata.dev()),
             gid: Some(metadata.gid()),
             file_type: FileType::from(metadata.file_type()),
             modified: metadata.modified().ok(),
@@ -157,6 +251,7 @@ impl From<StdMetadata> for Metadata {
             } else {
                 metadata.len()
             },
+            streams: None,
             symlink: None,
             uid: Some(metadata.uid()),
         }
@@ -166,8 +261,6 @@ impl From<StdMetadata> for Metadata {
 #[cfg(test)]
 mod test {
 
-    use std::time::{Duration, UNIX_EPOCH};
-
     use pretty_assertions::assert_eq;
 
     use super::super::UnixPexClass;
@@ -177,11 +270,14 @@ mod test {
     fn should_initialize_metadata() {
         let metadata = Metadata::default();
         assert!(metadata.accessed.is_none());
+        assert!(metadata.child_count.is_none());
         assert!(metadata.created.is_none());
+        assert!(metadata.dev.is_none());
         assert!(metadata.gid.is_none());
         assert!(metadata.mode.is_none());
         assert!(metadata.modified.is_none());
         assert_eq!(metadata.size, 0);
+        assert!(metadata.streams.is_none());
         assert!(metadata.symlink.is_none());
         assert_eq!(metadata.file_type, FileType::File);
         assert!(metadata.uid.is_none());
@@ -199,6 +295,7 @@ mod test {
         let metadata = Metadata::default()
             .accessed(accessed)
             .created(created)
+            .dev(2049)
             .gid(14)
             .mode(UnixPex::new(
                 UnixPexClass::from(6),
@@ -207,15 +304,21 @@ mod test {
             ))
             .modified(modified)
             .size(1024)
+            .streams(vec![String::from("Zone.Identifier")])
             .symlink(Path::new("/tmp/a.txt"))
             .file_type(FileType::Symlink)
             .uid(10);
         assert_eq!(metadata.accessed, Some(accessed));
         assert_eq!(metadata.created, Some(created));
+        assert_eq!(metadata.dev, Some(2049));
         assert_eq!(metadata.gid.unwrap(), 14);
         assert!(metadata.mode.is_some());
         assert_eq!(metadata.modified, Some(modified));
         assert_eq!(metadata.size, 1024);
+        assert_eq!(
+            metadata.streams.as_deref().unwrap(),
+            &[String::from("Zone.Identifier")]
+        );
         assert_eq!(metadata.is_symlink(), true);
         assert_eq!(metadata.is_dir(), false);
         assert_eq!(metadata.is_file(), false);
@@ -226,6 +329,31 @@ mod test {
         assert_eq!(metadata.uid.unwrap(), 10);
     }
 
+    #[test]
+    fn should_compare_metadata_content() {
+        let modified = UNIX_EPOCH.checked_add(Duration::from_secs(86400)).unwrap();
+        let metadata = Metadata::default()
+            .size(1024)
+            .modified(modified)
+            .mode(UnixPex::from(0o644))
+            .uid(1000)
+            .gid(1000);
+        let other = metadata.clone().accessed(SystemTime::now());
+        assert!(metadata.content_eq(&other));
+        assert!(metadata.content_eq_ignore_ownership(&other));
+        // only ownership differs
+        let other = metadata.clone().uid(1001).gid(1001);
+        assert!(!metadata.content_eq(&other));
+        assert!(metadata.content_eq_ignore_ownership(&other));
+        // size differs
+        let other = metadata.clone().size(2048);
+        assert!(!metadata.content_eq(&other));
+        assert!(!metadata.content_eq_ignore_ownership(&other));
+        // mode differs
+        let other = metadata.clone().mode(UnixPex::from(0o600));
+        assert!(!metadata.content_eq_ignore_ownership(&other));
+    }
+
     #[test]
     #[cfg(target_family = "windows")]
     fn should_make_metadata_from_std_metadata() {
@@ -253,4 +381,47 @@ mod test {
         assert!(metadata.uid.is_some());
         assert!(metadata.mode.is_some());
     }
+
+    #[test]
+    fn should_detect_special_bits() {
+        let setuid = Metadata::default().mode(UnixPex::from(0o4755));
+        assert!(setuid.is_setuid());
+        assert!(!setuid.is_setgid());
+        assert!(!setuid.is_sticky());
+        let setgid = Metadata::default().mode(UnixPex::from(0o2755));
+        assert!(!setgid.is_setuid());
+        assert!(setgid.is_setgid());
+        assert!(!setgid.is_sticky());
+        let sticky = Metadata::default().mode(UnixPex::from(0o1777));
+        assert!(!sticky.is_setuid());
+        assert!(!sticky.is_setgid());
+        assert!(sticky.is_sticky());
+        let plain = Metadata::default().mode(UnixPex::from(0o755));
+        assert!(!plain.is_setuid() && !plain.is_setgid() && !plain.is_sticky());
+        assert!(!Metadata::default().is_setuid());
+    }
+
+    #[test]
+    fn should_truncate_metadata_times() {
+        let modified = UNIX_EPOCH + Duration::from_secs(86400);
+        let metadata = Metadata::default()
+            .modified(modified + Duration::from_micros(1500))
+            .accessed(modified + Duration::from_millis(999))
+            .size(1024);
+        let other = Metadata::default()
+            .modified(modified + Duration::from_micros(250))
+            .accessed(modified)
+            .size(1024);
+        assert_ne!(metadata, other);
+        let truncated = metadata.truncated(Duration::from_secs(1));
+        assert_eq!(truncated, other.truncated(Duration::from_secs(1)));
+        assert_eq!(truncated.modified, Some(modified));
+        assert_eq!(truncated.accessed, Some(modified));
+        assert_eq!(truncated.size, 1024);
+        assert_eq!(
+            metadata.truncated(Duration::from_millis(1)).modified,
+            Some(modified + Duration::from_millis(1))
+        );
+        assert_eq!(metadata.truncated(Duration::ZERO), metadata);
+    }
 }
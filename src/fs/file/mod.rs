@@ -3,17 +3,38 @@
 //! file system types related to file entries and directories
 
 // -- ext
-use std::path::{Path, PathBuf};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::utils::path::{normalize, sanitize_for_local};
+use crate::{RemoteError, RemoteErrorType, RemoteResult};
 
 // -- mod
+#[cfg(feature = "dir-entry")]
+mod dir_entry;
+mod field_mask;
 mod file_type;
 mod metadata;
+mod path_status;
 mod permissions;
+mod size;
+mod symlink_chain;
+mod sync_state;
+#[cfg(feature = "serde")]
+pub mod unix_pex_octal;
 
 // -- export
+#[cfg(feature = "dir-entry")]
+pub use dir_entry::DirEntryLike;
+pub use field_mask::FieldMask;
 pub use file_type::FileType;
 pub use metadata::Metadata;
-pub use permissions::{UnixPex, UnixPexClass};
+pub use path_status::PathStatus;
+pub use permissions::{InvalidDigit, ParseUnixPexError, UnixPex, UnixPexClass};
+pub use size::{HumanSize, SizeUnit, UnitLabels};
+pub use symlink_chain::SymlinkChain;
+pub use sync_state::SyncState;
 
 /// A file represents an entity in the file system
 
@@ -26,6 +47,24 @@ pub struct File {
 }
 
 impl File {
+    /// Create a regular file at `path` with unknown attributes (see [`Metadata::default`]),
+    /// such as a placeholder for a file which hasn't been stat yet
+    pub fn default_file<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Create a directory at `path` with unknown attributes (see [`Metadata::default`]),
+    /// such as a placeholder for a directory which hasn't been stat yet
+    pub fn default_dir<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            metadata: Metadata::default().file_type(FileType::Directory),
+        }
+    }
+
     /// Get absolute path
     pub fn path(&self) -> &Path {
         self.path.as_path()
@@ -44,6 +83,15 @@ impl File {
         &self.metadata
     }
 
+    /// Get the amount of entries in the directory, if the file is a directory and the protocol reported it.
+    /// Returns `None` if the directory must be listed to count its entries
+    pub fn child_count(&self) -> Option<u64> {
+        match self.is_dir() {
+            true => self.metadata().child_count,
+            false => None,
+        }
+    }
+
     /// Get file type, if defined
     pub fn extension(&self) -> Option<String> {
         self.path()
@@ -51,6 +99,17 @@ impl File {
             .map(|x| x.to_string_lossy().to_string())
     }
 
+    /// Get file size decomposed into a value and a binary unit, such as `1.5 KiB`
+    pub fn human_size(&self) -> HumanSize {
+        HumanSize::from_bytes(self.metadata().size)
+    }
+
+    /// Get file size as a human readable string, formatted with `labels`.
+    /// Use [`UnitLabels::default`] for English labels
+    pub fn humanized_size(&self, labels: &UnitLabels) -> String {
+        self.human_size().format(labels)
+    }
+
     /// Returns whether the file is a directory
     pub fn is_dir(&self) -> bool {
         self.metadata().is_dir()
@@ -70,6 +129,91 @@ impl File {
     pub fn is_hidden(&self) -> bool {
         self.name().starts_with('.')
     }
+
+    /// Returns whether `self` and `other` have the same path, once both paths are normalized.
+    /// Repeated separators and `.` components are collapsed, so `/a//b` and `/a/./b` are equal.
+    /// Metadata is not compared.
+    pub fn path_eq(&self, other: &File) -> bool {
+        normalize(self.path()) == normalize(other.path())
+    }
+
+    /// Returns whether `self` and `other` are equal, comparing only the fields in `mask`.
+    /// Paths are compared once normalized, as in [`File::path_eq`]
+    pub fn eq_masked(&self, other: &File, mask: FieldMask) -> bool {
+        let (a, b) = (self.metadata(), other.metadata());
+        (!mask.contains(FieldMask::PATH) || self.path_eq(other))
+            && (!mask.contains(FieldMask::FILE_TYPE) || a.file_type == b.file_type)
+            && (!mask.contains(FieldMask::SIZE) || a.size == b.size)
+            && (!mask.contains(FieldMask::MTIME) || a.modified == b.modified)
+            && (!mask.contains(FieldMask::MODE) || a.mode == b.mode)
+            && (!mask.contains(FieldMask::OWNER) || (a.uid, a.gid) == (b.uid, b.gid))
+    }
+
+    /// Returns the path the file should be extracted to on the local file system,
+    /// re-rooting its path from `remote_root` to `local_root`.
+    ///
+    /// Each component of the relative path is sanitized with [`sanitize_for_local`] and joined with the separator of the host.
+    /// Returns `None` if the file is not under `remote_root`
+    pub fn local_path(&self, remote_root: &Path, local_root: &Path) -> Option<PathBuf> {
+        let relative = self.path().strip_prefix(remote_root).ok()?;
+        let mut local = local_root.to_path_buf();
+        for component in relative.components() {
+            match component {
+                Component::Normal(name) => local.push(sanitize_for_local(&name.to_string_lossy())),
+                Component::ParentDir => local.push(sanitize_for_local("..")),
+                _ => {}
+            }
+        }
+        Some(local)
+    }
+
+    /// Compare the file with the `local` file, classifying which one is newer for a bidirectional sync.
+    /// Modify times closer than [`SyncState::DEFAULT_TOLERANCE`] are considered equal; see [`SyncState::between`]
+    pub fn compare_with_local(&self, local: &Path) -> io::Result<SyncState> {
+        self.compare_with_local_within(local, SyncState::DEFAULT_TOLERANCE)
+    }
+
+    /// Same as [`File::compare_with_local`], considering equal the modify times closer than `tolerance`
+    pub fn compare_with_local_within(
+        &self,
+        local: &Path,
+        tolerance: Duration,
+    ) -> io::Result<SyncState> {
+        SyncState::between(Some(self), local, tolerance)
+    }
+
+    /// Returns a copy of the file, with its metadata modified by `f`.
+    /// Useful to change a single field before calling [`crate::RemoteFs::setstat`],
+    /// such as `file.update_metadata(|m| m.mode = Some(UnixPex::from(0o644)))`
+    pub fn update_metadata(&self, f: impl FnOnce(&mut Metadata)) -> File {
+        let mut file = self.clone();
+        f(&mut file.metadata);
+        file
+    }
+
+    /// Returns the path the file would have once renamed to `new_name`, which replaces the last component of the path.
+    ///
+    /// Fails with [`RemoteErrorType::BadFile`] if `new_name` is empty, is `.` or `..`, or contains a path separator,
+    /// since renaming it would move the file to another directory. Renaming the root directory fails as well.
+    pub fn renamed_to(&self, new_name: &str) -> RemoteResult<PathBuf> {
+        if new_name.is_empty()
+            || new_name == "."
+            || new_name == ".."
+            || new_name.contains(['/', '\\'])
+        {
+            return Err(RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                format!("invalid file name: `{new_name}`"),
+            ));
+        }
+        match self.path().parent() {
+            Some(parent) => Ok(parent.join(new_name)),
+            None => Err(RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                "cannot rename the root directory",
+            )),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -94,6 +238,37 @@ mod tests {
         assert_eq!(entry.is_hidden(), false);
     }
 
+    #[test]
+    fn should_create_placeholder_files() {
+        let file = File::default_file("/home/omar/readme.md");
+        assert_eq!(file.path(), Path::new("/home/omar/readme.md"));
+        assert!(file.is_file());
+        assert_eq!(file.metadata(), &Metadata::default());
+        let dir = File::default_dir(PathBuf::from("/home/omar"));
+        assert_eq!(dir.path(), Path::new("/home/omar"));
+        assert!(dir.is_dir());
+        assert_eq!(dir.metadata().size, 0);
+        assert!(dir.metadata().mode.is_none());
+        assert!(dir.metadata().modified.is_none());
+        assert!(dir.child_count().is_none());
+    }
+
+    #[test]
+    fn should_get_humanized_size() {
+        let entry = File {
+            path: PathBuf::from("/bar.txt"),
+            metadata: Metadata::default().size(1536),
+        };
+        assert_eq!(
+            entry.human_size(),
+            HumanSize {
+                value: 1.5,
+                unit: SizeUnit::KiB
+            }
+        );
+        assert_eq!(entry.humanized_size(&UnitLabels::default()), "1.5 KiB");
+    }
+
     #[test]
     fn should_return_is_hidden_for_hidden_files() {
         let entry = File {
@@ -102,4 +277,213 @@ mod tests {
         };
         assert_eq!(entry.is_hidden(), true);
     }
+
+    #[test]
+    fn should_compare_normalized_paths() {
+        let entry = File {
+            path: PathBuf::from("/a/b"),
+            metadata: Metadata::default().size(1),
+        };
+        for path in ["/a//b", "/a/./b", "/a/b/", "//a/b"] {
+            let other = File {
+                path: PathBuf::from(path),
+                metadata: Metadata::default().size(2),
+            };
+            assert!(entry.path_eq(&other), "{path}");
+        }
+        let other = File {
+            path: PathBuf::from("/a/c"),
+            metadata: Metadata::default().size(1),
+        };
+        assert!(!entry.path_eq(&other));
+    }
+
+    #[test]
+    fn should_update_metadata() {
+        let entry = File {
+            path: PathBuf::from("/bar.txt"),
+            metadata: Metadata::default()
+                .size(8192)
+                .mode(UnixPex::from(0o644))
+                .uid(1000)
+                .gid(100),
+        };
+        let updated = entry.update_metadata(|m| m.mode = Some(UnixPex::from(0o600)));
+        assert_eq!(updated.path(), entry.path());
+        assert_eq!(updated.metadata().mode, Some(UnixPex::from(0o600)));
+        assert_eq!(updated.metadata().size, 8192);
+        assert_eq!(updated.metadata().uid, Some(1000));
+        assert_eq!(updated.metadata().gid, Some(100));
+        assert_eq!(updated.metadata().file_type, FileType::File);
+        // original is untouched
+        assert_eq!(entry.metadata().mode, Some(UnixPex::from(0o644)));
+    }
+
+    #[test]
+    fn should_compute_renamed_path() {
+        let entry = File {
+            path: PathBuf::from("/home/omar/readme.md"),
+            metadata: Metadata::default(),
+        };
+        assert_eq!(
+            entry.renamed_to("README.md").unwrap(),
+            PathBuf::from("/home/omar/README.md")
+        );
+        let root = File {
+            path: PathBuf::from("/"),
+            metadata: Metadata::default().file_type(FileType::Directory),
+        };
+        assert_eq!(
+            root.renamed_to("foo").unwrap_err().kind,
+            RemoteErrorType::BadFile
+        );
+    }
+
+    #[test]
+    fn should_reject_rename_moving_file() {
+        let entry = File {
+            path: PathBuf::from("/home/omar/readme.md"),
+            metadata: Metadata::default(),
+        };
+        for name in [
+            "../readme.md",
+            "docs/readme.md",
+            "docs\\readme.md",
+            "",
+            ".",
+            "..",
+        ] {
+            assert_eq!(
+                entry.renamed_to(name).unwrap_err().kind,
+                RemoteErrorType::BadFile,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_get_local_path() {
+        let entry = File {
+            path: PathBuf::from("/srv/www/docs/readme.md"),
+            metadata: Metadata::default(),
+        };
+        assert!(entry
+            .local_path(Path::new("/home"), Path::new("/tmp"))
+            .is_none());
+        let root = File {
+            path: PathBuf::from("/srv/www"),
+            metadata: Metadata::default().file_type(FileType::Directory),
+        };
+        assert_eq!(
+            root.local_path(Path::new("/srv/www"), Path::new("/tmp/www"))
+                .unwrap(),
+            PathBuf::from("/tmp/www")
+        );
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn should_get_local_path_on_unix() {
+        let entry = File {
+            path: PathBuf::from("/srv/www/docs/a:b.md"),
+            metadata: Metadata::default(),
+        };
+        assert_eq!(
+            entry
+                .local_path(Path::new("/srv/www"), Path::new("/tmp/www"))
+                .unwrap(),
+            PathBuf::from("/tmp/www/docs/a:b.md")
+        );
+        let entry = File {
+            path: PathBuf::from("/srv/www/../etc/passwd"),
+            metadata: Metadata::default(),
+        };
+        assert_eq!(
+            entry
+                .local_path(Path::new("/srv/www"), Path::new("/tmp/www"))
+                .unwrap(),
+            PathBuf::from("/tmp/www/_/etc/passwd")
+        );
+    }
+
+    #[test]
+    #[cfg(target_family = "windows")]
+    fn should_get_local_path_on_windows() {
+        let entry = File {
+            path: PathBuf::from("/srv/www/docs/a:b.md"),
+            metadata: Metadata::default(),
+        };
+        assert_eq!(
+            entry
+                .local_path(Path::new("/srv/www"), Path::new("C:\\Users\\omar"))
+                .unwrap(),
+            PathBuf::from("C:\\Users\\omar\\docs\\a_b.md")
+        );
+        let entry = File {
+            path: PathBuf::from("/srv/www/CON/aux.txt"),
+            metadata: Metadata::default(),
+        };
+        assert_eq!(
+            entry
+                .local_path(Path::new("/srv/www"), Path::new("C:\\Users\\omar"))
+                .unwrap(),
+            PathBuf::from("C:\\Users\\omar\\_CON\\_aux.txt")
+        );
+    }
+
+    #[test]
+    fn should_get_child_count() {
+        let dir = File {
+            path: PathBuf::from("/home"),
+            metadata: Metadata::default()
+                .file_type(FileType::Directory)
+                .child_count(42),
+        };
+        assert_eq!(dir.child_count(), Some(42));
+        let dir = File {
+            path: PathBuf::from("/home"),
+            metadata: Metadata::default().file_type(FileType::Directory),
+        };
+        assert_eq!(dir.child_count(), None);
+        let file = File {
+            path: PathBuf::from("/home/a.txt"),
+            metadata: Metadata::default().child_count(42),
+        };
+        assert_eq!(file.child_count(), None);
+    }
+
+    #[test]
+    fn should_compare_masked_fields() {
+        let entry = File {
+            path: PathBuf::from("/a.txt"),
+            metadata: Metadata::default()
+                .size(1024)
+                .modified(std::time::UNIX_EPOCH)
+                .mode(UnixPex::from(0o644))
+                .uid(1000)
+                .gid(1000),
+        };
+        assert!(entry.eq_masked(&entry.clone(), FieldMask::ALL));
+        let other = File {
+            path: PathBuf::from("/b.txt"),
+            ..entry.clone()
+        };
+        assert!(!other.eq_masked(&entry, FieldMask::ALL));
+        assert!(other.eq_masked(&entry, !FieldMask::PATH));
+        let other = entry.update_metadata(|m| m.size = 2048);
+        assert!(!other.eq_masked(&entry, FieldMask::SIZE));
+        assert!(other.eq_masked(&entry, FieldMask::PATH | FieldMask::MODE));
+        let other = entry.update_metadata(|m| m.modified = None);
+        assert!(!other.eq_masked(&entry, FieldMask::MTIME));
+        assert!(other.eq_masked(&entry, !FieldMask::MTIME));
+        let other = entry.update_metadata(|m| m.mode = Some(UnixPex::from(0o600)));
+        assert!(!other.eq_masked(&entry, FieldMask::MODE));
+        assert!(other.eq_masked(&entry, FieldMask::SIZE | FieldMask::OWNER));
+        let other = entry.update_metadata(|m| m.gid = Some(100));
+        assert!(!other.eq_masked(&entry, FieldMask::OWNER));
+        assert!(other.eq_masked(&entry, !FieldMask::OWNER));
+        let other = entry.update_metadata(|m| m.file_type = FileType::Symlink);
+        assert!(!other.eq_masked(&entry, FieldMask::FILE_TYPE));
+        assert!(other.eq_masked(&entry, FieldMask::NONE));
+    }
 }
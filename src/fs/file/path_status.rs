@@ -0,0 +1,84 @@
+//! ## Path status
+//!
+//! describes whether a path exists and its kind
+
+use std::path::PathBuf;
+
+use super::{File, FileType};
+
+/// Status of a path on the remote file system, as returned by [`crate::RemoteFs::probe_path`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathStatus {
+    /// Nothing exists at path
+    Missing,
+    /// Path is a regular file
+    File,
+    /// Path is a directory
+    Dir,
+    /// Path is a symbolic link, pointing to `target` if known
+    Symlink { target: Option<PathBuf> },
+    /// Path is of another kind of file, not covered by the variants above
+    Other(FileType),
+}
+
+impl PathStatus {
+    /// Returns whether something exists at path
+    pub fn exists(&self) -> bool {
+        !matches!(self, Self::Missing)
+    }
+}
+
+impl From<File> for PathStatus {
+    fn from(file: File) -> Self {
+        match file.metadata.file_type {
+            FileType::File => Self::File,
+            FileType::Directory => Self::Dir,
+            FileType::Symlink => Self::Symlink {
+                target: file.metadata.symlink,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::fs::Metadata;
+
+    fn entry(metadata: Metadata) -> File {
+        File {
+            path: PathBuf::from("/a"),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn should_make_path_status_from_file() {
+        assert_eq!(
+            PathStatus::from(entry(Metadata::default())),
+            PathStatus::File
+        );
+        assert_eq!(
+            PathStatus::from(entry(Metadata::default().file_type(FileType::Directory))),
+            PathStatus::Dir
+        );
+        assert_eq!(
+            PathStatus::from(entry(
+                Metadata::default()
+                    .file_type(FileType::Symlink)
+                    .symlink(Path::new("/b"))
+            )),
+            PathStatus::Symlink {
+                target: Some(PathBuf::from("/b"))
+            }
+        );
+        assert!(PathStatus::Dir.exists());
+        assert!(PathStatus::Other(FileType::File).exists());
+        assert!(!PathStatus::Missing.exists());
+    }
+}
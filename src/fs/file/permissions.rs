@@ -2,14 +2,66 @@
 //!
 //! POSIX permissions
 
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// Error returned when parsing a [`UnixPex`] from a string fails
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ParseUnixPexError {
+    #[error("invalid octal permissions: `{0}`")]
+    InvalidOctal(String),
+    #[error("invalid symbolic permissions: `{0}`")]
+    InvalidSymbolic(String),
+}
+
+/// Error returned by [`UnixPexClass::from_octal_digit`] when the digit is greater than `7`
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("invalid octal digit: `{0}`")]
+pub struct InvalidDigit(pub u8);
+
 /// Describes the permissions on POSIX system.
+///
+/// Besides the permission classes, the special bits (setuid, setgid and sticky) are stored,
+/// as the leading octal digit of the mode (e.g. `0o4755`).
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-pub struct UnixPex(UnixPexClass, UnixPexClass, UnixPexClass);
+pub struct UnixPex(UnixPexClass, UnixPexClass, UnixPexClass, u8);
 
 impl UnixPex {
-    /// Create a new `UnixPex`
-    pub fn new(user: UnixPexClass, group: UnixPexClass, others: UnixPexClass) -> Self {
-        Self(user, group, others)
+    const SETUID: u8 = 0o4;
+    const SETGID: u8 = 0o2;
+    const STICKY: u8 = 0o1;
+
+    /// `rw-r--r--`, the usual mode of regular files
+    pub const FILE: Self = Self::from_octal(0o644);
+    /// `rwxr-xr-x`, the usual mode of directories and executables
+    pub const DIR: Self = Self::from_octal(0o755);
+    /// `rw-------`, for files readable only by their owner
+    pub const PRIVATE_FILE: Self = Self::from_octal(0o600);
+    /// `rwx------`, for directories accessible only by their owner
+    pub const PRIVATE_DIR: Self = Self::from_octal(0o700);
+
+    /// Create a new `UnixPex`, with no special bit set
+    pub const fn new(user: UnixPexClass, group: UnixPexClass, others: UnixPexClass) -> Self {
+        Self(user, group, others, 0)
+    }
+
+    /// Create `UnixPex` from an octal `mode`, such as `0o644` or `0o4755` with special bits.
+    /// Bits above `0o7777` are ignored. Usable in `const` contexts:
+    /// `const MODE: UnixPex = UnixPex::from_octal(0o644);`
+    pub const fn from_octal(mode: u32) -> Self {
+        Self(
+            UnixPexClass::from_bits(((mode >> 6) & 0x7) as u8),
+            UnixPexClass::from_bits(((mode >> 3) & 0x7) as u8),
+            UnixPexClass::from_bits((mode & 0x7) as u8),
+            ((mode >> 9) & 0x7) as u8,
+        )
+    }
+
+    /// Construct `UnixPex` with the setuid, setgid and sticky special bits
+    pub fn with_special(mut self, setuid: bool, setgid: bool, sticky: bool) -> Self {
+        self.3 = ((setuid as u8) << 2) | ((setgid as u8) << 1) | (sticky as u8);
+        self
     }
 
     /// Returns unix permissions class for `user`
@@ -26,21 +78,135 @@ impl UnixPex {
     pub fn others(&self) -> UnixPexClass {
         self.2
     }
+
+    /// Returns whether the setuid bit is set
+    pub fn is_setuid(&self) -> bool {
+        self.3 & Self::SETUID != 0
+    }
+
+    /// Returns whether the setgid bit is set
+    pub fn is_setgid(&self) -> bool {
+        self.3 & Self::SETGID != 0
+    }
+
+    /// Returns whether the sticky bit is set
+    pub fn is_sticky(&self) -> bool {
+        self.3 & Self::STICKY != 0
+    }
+
+    /// Parse permissions from a string, either in octal notation (`"0755"`, `"755"`, `"0o755"` or `"4755"` with special bits)
+    /// or in symbolic notation (`"rwxr-xr-x"`).
+    /// Strings made only of digits, or starting with `0o`, are parsed as octal; any other string is parsed as symbolic
+    pub fn parse(s: &str) -> Result<Self, ParseUnixPexError> {
+        let is_octal =
+            s.starts_with("0o") || (!s.is_empty() && s.chars().all(|c| c.is_ascii_digit()));
+        match is_octal {
+            true => Self::parse_octal(s),
+            false => Self::parse_symbolic(s),
+        }
+    }
+
+    /// Parse permissions in octal notation, such as `"0755"`, `"755"`, `"0o755"` or `"4755"`
+    pub(crate) fn parse_octal(s: &str) -> Result<Self, ParseUnixPexError> {
+        let digits = s.strip_prefix("0o").unwrap_or(s);
+        // `from_str_radix` accepts a leading sign
+        if digits.is_empty() || !digits.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+            return Err(ParseUnixPexError::InvalidOctal(s.to_string()));
+        }
+        match u32::from_str_radix(digits, 8) {
+            Ok(mode) if mode <= 0o7777 => Ok(Self::from(mode)),
+            _ => Err(ParseUnixPexError::InvalidOctal(s.to_string())),
+        }
+    }
+
+    /// Parse permissions in symbolic notation, such as `"rwxr-xr-x"`
+    fn parse_symbolic(s: &str) -> Result<Self, ParseUnixPexError> {
+        let err = || ParseUnixPexError::InvalidSymbolic(s.to_string());
+        let chars: Vec<char> = s.chars().collect();
+        if chars.len() != 9 {
+            return Err(err());
+        }
+        let mut mode = 0;
+        for (i, c) in chars.iter().enumerate() {
+            let bit = match (i % 3, c) {
+                (_, '-') => false,
+                (0, 'r') | (1, 'w') | (2, 'x') => true,
+                _ => return Err(err()),
+            };
+            mode |= (bit as u32) << (8 - i);
+        }
+        Ok(Self::from(mode))
+    }
+
+    /// Returns whether `self` grants no permission which is not granted by `max`
+    pub fn is_at_most(&self, max: UnixPex) -> bool {
+        u32::from(*self) & !u32::from(max) == 0
+    }
+
+    /// Returns whether `self` grants at least all the permissions granted by `min`
+    pub fn is_at_least(&self, min: UnixPex) -> bool {
+        u32::from(*self) & u32::from(min) == u32::from(min)
+    }
+
+    /// Describe the permissions granted by `self` which exceed `max`.
+    ///
+    /// Returns a message for each exceeding permission, listing the classes which have it
+    /// (e.g. `group/others have read` for `0o644` against `0o600`),
+    /// followed by a message for each exceeding special bit (e.g. `setuid is set` for `0o4600` against `0o600`).
+    /// The returned list is empty if `self` is at most `max`
+    pub fn diagnose_against(&self, max: UnixPex) -> Vec<String> {
+        let classes = [
+            ("user", self.user(), max.user()),
+            ("group", self.group(), max.group()),
+            ("others", self.others(), max.others()),
+        ];
+        let special = [
+            ("setuid", Self::SETUID),
+            ("setgid", Self::SETGID),
+            ("sticky", Self::STICKY),
+        ]
+        .into_iter()
+        .filter(|(_, bit)| self.3 & !max.3 & bit != 0)
+        .map(|(name, _)| format!("{} is set", name));
+        [("read", 0o4), ("write", 0o2), ("execute", 0o1)]
+            .iter()
+            .filter_map(|(permission, bit)| {
+                let offending: Vec<&str> = classes
+                    .iter()
+                    .filter(|(_, class, max)| class.as_byte() & !max.as_byte() & bit != 0)
+                    .map(|(name, _, _)| *name)
+                    .collect();
+                match offending.len() {
+                    0 => None,
+                    1 => Some(format!("{} has {}", offending[0], permission)),
+                    _ => Some(format!("{} have {}", offending.join("/"), permission)),
+                }
+            })
+            .chain(special)
+            .collect()
+    }
+}
+
+impl FromStr for UnixPex {
+    type Err = ParseUnixPexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
 }
 
 impl From<UnixPex> for u32 {
     fn from(pex: UnixPex) -> Self {
-        (u32::from(pex.0) << 6) + (u32::from(pex.1) << 3) + u32::from(pex.2)
+        (u32::from(pex.3) << 9)
+            + (u32::from(pex.0) << 6)
+            + (u32::from(pex.1) << 3)
+            + u32::from(pex.2)
     }
 }
 
 impl From<u32> for UnixPex {
     fn from(x: u32) -> Self {
-        UnixPex::new(
-            UnixPexClass::from(((x >> 6) & 0x7) as u8),
-            UnixPexClass::from(((x >> 3) & 0x7) as u8),
-            UnixPexClass::from((x & 0x7) as u8),
-        )
+        Self::from_octal(x)
     }
 }
 
@@ -53,8 +219,19 @@ pub struct UnixPexClass {
 }
 
 impl UnixPexClass {
+    /// `---`
+    pub const NONE: Self = Self::new(false, false, false);
+    /// `r--`
+    pub const READ: Self = Self::new(true, false, false);
+    /// `rw-`
+    pub const READ_WRITE: Self = Self::new(true, true, false);
+    /// `r-x`
+    pub const READ_EXECUTE: Self = Self::new(true, false, true);
+    /// `rwx`
+    pub const ALL: Self = Self::new(true, true, true);
+
     /// Instantiates a new `UnixPex`
-    pub fn new(read: bool, write: bool, execute: bool) -> Self {
+    pub const fn new(read: bool, write: bool, execute: bool) -> Self {
         Self {
             read,
             write,
@@ -62,21 +239,43 @@ impl UnixPexClass {
         }
     }
 
+    /// Create a class from the lowest 3 bits of `bits`
+    const fn from_bits(bits: u8) -> Self {
+        Self {
+            read: ((bits >> 2) & 0x01) != 0,
+            write: ((bits >> 1) & 0x01) != 0,
+            execute: (bits & 0x01) != 0,
+        }
+    }
+
     /// Returns whether user can read
-    pub fn read(&self) -> bool {
+    pub const fn read(&self) -> bool {
         self.read
     }
 
     /// Returns whether user can write
-    pub fn write(&self) -> bool {
+    pub const fn write(&self) -> bool {
         self.write
     }
 
     /// Returns whether user can execute
-    pub fn execute(&self) -> bool {
+    pub const fn execute(&self) -> bool {
         self.execute
     }
 
+    /// Create a class from a single octal digit (`0`-`7`), such as `5` for `r-x`
+    pub fn from_octal_digit(digit: u8) -> Result<Self, InvalidDigit> {
+        match digit {
+            0..=7 => Ok(Self::from(digit)),
+            _ => Err(InvalidDigit(digit)),
+        }
+    }
+
+    /// Returns the octal digit (`0`-`7`) representing the class
+    pub fn as_octal_digit(&self) -> u8 {
+        self.as_byte()
+    }
+
     /// Convert permission to byte as on POSIX systems
     pub fn as_byte(&self) -> u8 {
         ((self.read as u8) << 2) + ((self.write as u8) << 1) + (self.execute as u8)
@@ -85,11 +284,7 @@ impl UnixPexClass {
 
 impl From<u8> for UnixPexClass {
     fn from(bits: u8) -> Self {
-        Self {
-            read: ((bits >> 2) & 0x01) != 0,
-            write: ((bits >> 1) & 0x01) != 0,
-            execute: (bits & 0x01) != 0,
-        }
+        Self::from_bits(bits)
     }
 }
 
@@ -130,6 +325,30 @@ mod test {
         assert_eq!(pex.as_byte(), 7);
     }
 
+    #[test]
+    fn should_convert_unix_pex_class_from_and_to_octal_digit() {
+        let expected = [
+            (0, false, false, false),
+            (1, false, false, true),
+            (2, false, true, false),
+            (3, false, true, true),
+            (4, true, false, false),
+            (5, true, false, true),
+            (6, true, true, false),
+            (7, true, true, true),
+        ];
+        for (digit, read, write, execute) in expected {
+            let class = UnixPexClass::from_octal_digit(digit).unwrap();
+            assert_eq!(class, UnixPexClass::new(read, write, execute));
+            assert_eq!(class.as_octal_digit(), digit);
+        }
+        assert_eq!(
+            UnixPexClass::from_octal_digit(8).unwrap_err(),
+            InvalidDigit(8)
+        );
+        assert_eq!(InvalidDigit(9).to_string(), "invalid octal digit: `9`");
+    }
+
     #[test]
     fn should_create_unix_pex() {
         let pex = UnixPex::new(
@@ -169,4 +388,139 @@ mod test {
             )
         );
     }
+
+    #[test]
+    fn should_create_unix_pex_in_const_context() {
+        const MODE: UnixPex = UnixPex::from_octal(0o640);
+        const CLASS: UnixPexClass = UnixPexClass::READ_EXECUTE;
+        assert_eq!(MODE, UnixPex::from(0o640));
+        assert_eq!(MODE.user(), UnixPexClass::READ_WRITE);
+        assert_eq!(MODE.group(), UnixPexClass::READ);
+        assert_eq!(MODE.others(), UnixPexClass::NONE);
+        assert_eq!(CLASS.as_octal_digit(), 5);
+        assert_eq!(UnixPexClass::ALL.as_octal_digit(), 7);
+        assert_eq!(u32::from(UnixPex::FILE), 0o644);
+        assert_eq!(u32::from(UnixPex::DIR), 0o755);
+        assert_eq!(u32::from(UnixPex::PRIVATE_FILE), 0o600);
+        assert_eq!(u32::from(UnixPex::PRIVATE_DIR), 0o700);
+        assert_eq!(u32::from(UnixPex::from_octal(0o4755)), 0o4755);
+        assert_eq!(u32::from(UnixPex::from_octal(0o17777)), 0o7777);
+    }
+
+    #[test]
+    fn should_convert_special_bits() {
+        let pex = UnixPex::from(0o4755);
+        assert!(pex.is_setuid());
+        assert!(!pex.is_setgid());
+        assert!(!pex.is_sticky());
+        assert_eq!(pex.user().as_byte(), 7);
+        assert_eq!(u32::from(pex), 0o4755);
+        let pex = UnixPex::from(0o2755);
+        assert!(!pex.is_setuid());
+        assert!(pex.is_setgid());
+        assert!(!pex.is_sticky());
+        let pex = UnixPex::from(0o1777);
+        assert!(!pex.is_setuid());
+        assert!(!pex.is_setgid());
+        assert!(pex.is_sticky());
+        assert_eq!(u32::from(pex), 0o1777);
+        assert_eq!(
+            UnixPex::from(0o755).with_special(true, true, false),
+            UnixPex::from(0o6755)
+        );
+        assert_ne!(UnixPex::from(0o4755), UnixPex::from(0o755));
+        assert_eq!(UnixPex::parse("4755").unwrap(), UnixPex::from(0o4755));
+        assert_eq!(UnixPex::parse("0o1777").unwrap(), UnixPex::from(0o1777));
+    }
+
+    #[test]
+    fn should_compare_unix_pex_against_limits() {
+        let pex = UnixPex::from(0o644);
+        assert!(!pex.is_at_most(UnixPex::from(0o600)));
+        assert!(pex.is_at_most(UnixPex::from(0o644)));
+        assert!(pex.is_at_most(UnixPex::from(0o755)));
+        assert!(pex.is_at_least(UnixPex::from(0o600)));
+        assert!(pex.is_at_least(UnixPex::from(0o444)));
+        assert!(!pex.is_at_least(UnixPex::from(0o700)));
+    }
+
+    #[test]
+    fn should_diagnose_unix_pex_against_max() {
+        assert_eq!(
+            UnixPex::from(0o644).diagnose_against(UnixPex::from(0o600)),
+            vec![String::from("group/others have read")]
+        );
+        assert_eq!(
+            UnixPex::from(0o775).diagnose_against(UnixPex::from(0o640)),
+            vec![
+                String::from("others has read"),
+                String::from("group has write"),
+                String::from("user/group/others have execute"),
+            ]
+        );
+        assert!(UnixPex::from(0o600)
+            .diagnose_against(UnixPex::from(0o600))
+            .is_empty());
+        // special bits
+        assert!(!UnixPex::from(0o4600).is_at_most(UnixPex::from(0o600)));
+        assert_eq!(
+            UnixPex::from(0o4600).diagnose_against(UnixPex::from(0o600)),
+            vec![String::from("setuid is set")]
+        );
+        assert_eq!(
+            UnixPex::from(0o3644).diagnose_against(UnixPex::from(0o1600)),
+            vec![
+                String::from("group/others have read"),
+                String::from("setgid is set"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_parse_unix_pex() {
+        assert_eq!(UnixPex::parse("0755").unwrap(), UnixPex::from(0o755));
+        assert_eq!(UnixPex::parse("755").unwrap(), UnixPex::from(0o755));
+        assert_eq!(UnixPex::parse("0o644").unwrap(), UnixPex::from(0o644));
+        assert_eq!(UnixPex::parse("rwxr-xr-x").unwrap(), UnixPex::from(0o755));
+        assert_eq!(UnixPex::parse("rw-r-----").unwrap(), UnixPex::from(0o640));
+        assert_eq!(UnixPex::parse("---------").unwrap(), UnixPex::from(0));
+        assert_eq!("0600".parse::<UnixPex>().unwrap(), UnixPex::from(0o600));
+    }
+
+    #[test]
+    fn should_not_parse_malformed_unix_pex() {
+        assert_eq!(
+            UnixPex::parse("0789").unwrap_err(),
+            ParseUnixPexError::InvalidOctal(String::from("0789"))
+        );
+        assert_eq!(
+            UnixPex::parse("17777").unwrap_err(),
+            ParseUnixPexError::InvalidOctal(String::from("17777"))
+        );
+        assert_eq!(
+            UnixPex::parse("0o").unwrap_err(),
+            ParseUnixPexError::InvalidOctal(String::from("0o"))
+        );
+        assert_eq!(
+            UnixPex::parse_octal("+755").unwrap_err(),
+            ParseUnixPexError::InvalidOctal(String::from("+755"))
+        );
+        assert_eq!(
+            UnixPex::parse("0o+755").unwrap_err(),
+            ParseUnixPexError::InvalidOctal(String::from("0o+755"))
+        );
+        assert_eq!(
+            UnixPex::parse("rwxr-xr-").unwrap_err(),
+            ParseUnixPexError::InvalidSymbolic(String::from("rwxr-xr-"))
+        );
+        assert_eq!(
+            UnixPex::parse("wrxr-xr-x").unwrap_err(),
+            ParseUnixPexError::InvalidSymbolic(String::from("wrxr-xr-x"))
+        );
+        assert!(UnixPex::parse("").is_err());
+        assert_eq!(
+            UnixPex::parse("rwxr-xr-").unwrap_err().to_string(),
+            "invalid symbolic permissions: `rwxr-xr-`"
+        );
+    }
 }
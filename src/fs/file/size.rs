@@ -0,0 +1,205 @@
+//! ## Size
+//!
+//! human readable file sizes
+
+use std::fmt;
+
+/// Binary unit of a [`HumanSize`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SizeUnit {
+    /// Bytes
+    Bytes,
+    /// 1024 bytes
+    KiB,
+    /// 1024 KiB
+    MiB,
+    /// 1024 MiB
+    GiB,
+    /// 1024 GiB
+    TiB,
+    /// 1024 TiB
+    PiB,
+    /// 1024 PiB
+    EiB,
+}
+
+impl SizeUnit {
+    const UNITS: [SizeUnit; 7] = [
+        Self::Bytes,
+        Self::KiB,
+        Self::MiB,
+        Self::GiB,
+        Self::TiB,
+        Self::PiB,
+        Self::EiB,
+    ];
+}
+
+/// Labels used to format a [`HumanSize`]; implement localization by providing translated labels.
+/// The default labels are in English
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnitLabels {
+    /// Label for a size of exactly one byte
+    pub byte: String,
+    /// Label for any other size in bytes
+    pub bytes: String,
+    /// Label for [`SizeUnit::KiB`]
+    pub kib: String,
+    /// Label for [`SizeUnit::MiB`]
+    pub mib: String,
+    /// Label for [`SizeUnit::GiB`]
+    pub gib: String,
+    /// Label for [`SizeUnit::TiB`]
+    pub tib: String,
+    /// Label for [`SizeUnit::PiB`]
+    pub pib: String,
+    /// Label for [`SizeUnit::EiB`]
+    pub eib: String,
+}
+
+impl Default for UnitLabels {
+    fn default() -> Self {
+        Self {
+            byte: String::from("byte"),
+            bytes: String::from("bytes"),
+            kib: String::from("KiB"),
+            mib: String::from("MiB"),
+            gib: String::from("GiB"),
+            tib: String::from("TiB"),
+            pib: String::from("PiB"),
+            eib: String::from("EiB"),
+        }
+    }
+}
+
+impl UnitLabels {
+    /// Get the label for `value` in `unit`
+    pub fn label(&self, value: f64, unit: SizeUnit) -> &str {
+        match unit {
+            SizeUnit::Bytes if value == 1.0 => &self.byte,
+            SizeUnit::Bytes => &self.bytes,
+            SizeUnit::KiB => &self.kib,
+            SizeUnit::MiB => &self.mib,
+            SizeUnit::GiB => &self.gib,
+            SizeUnit::TiB => &self.tib,
+            SizeUnit::PiB => &self.pib,
+            SizeUnit::EiB => &self.eib,
+        }
+    }
+}
+
+/// A size decomposed into a numeric value and the largest binary unit for which the value is at least `1`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HumanSize {
+    /// Size in `unit`
+    pub value: f64,
+    /// Unit of `value`
+    pub unit: SizeUnit,
+}
+
+impl HumanSize {
+    /// Decompose a size of `bytes`
+    pub fn from_bytes(bytes: u64) -> Self {
+        let mut value = bytes as f64;
+        let mut unit = SizeUnit::Bytes;
+        for next in SizeUnit::UNITS.iter().skip(1) {
+            if value < 1024.0 {
+                break;
+            }
+            value /= 1024.0;
+            unit = *next;
+        }
+        Self { value, unit }
+    }
+
+    /// Format the size with `labels`, such as `1.5 KiB` or `512 bytes`.
+    /// Sizes in bytes have no decimals, other sizes have one decimal
+    pub fn format(&self, labels: &UnitLabels) -> String {
+        let label = labels.label(self.value, self.unit);
+        match self.unit {
+            SizeUnit::Bytes => format!("{} {}", self.value, label),
+            _ => format!("{:.1} {}", self.value, label),
+        }
+    }
+}
+
+impl From<u64> for HumanSize {
+    fn from(bytes: u64) -> Self {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl fmt::Display for HumanSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(&UnitLabels::default()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_decompose_size() {
+        assert_eq!(
+            HumanSize::from_bytes(0),
+            HumanSize {
+                value: 0.0,
+                unit: SizeUnit::Bytes
+            }
+        );
+        assert_eq!(
+            HumanSize::from_bytes(1023),
+            HumanSize {
+                value: 1023.0,
+                unit: SizeUnit::Bytes
+            }
+        );
+        assert_eq!(
+            HumanSize::from_bytes(1536),
+            HumanSize {
+                value: 1.5,
+                unit: SizeUnit::KiB
+            }
+        );
+        assert_eq!(
+            HumanSize::from_bytes(5 * 1024 * 1024 * 1024),
+            HumanSize {
+                value: 5.0,
+                unit: SizeUnit::GiB
+            }
+        );
+        assert_eq!(HumanSize::from_bytes(u64::MAX).unit, SizeUnit::EiB);
+    }
+
+    #[test]
+    fn should_format_size() {
+        assert_eq!(HumanSize::from_bytes(1).to_string(), "1 byte");
+        assert_eq!(HumanSize::from_bytes(512).to_string(), "512 bytes");
+        assert_eq!(HumanSize::from_bytes(1536).to_string(), "1.5 KiB");
+        assert_eq!(
+            HumanSize::from_bytes(3 * 1024 * 1024).to_string(),
+            "3.0 MiB"
+        );
+    }
+
+    #[test]
+    fn should_format_size_with_localized_labels() {
+        let labels = UnitLabels {
+            byte: String::from("octet"),
+            bytes: String::from("octets"),
+            kib: String::from("Kio"),
+            mib: String::from("Mio"),
+            gib: String::from("Gio"),
+            tib: String::from("Tio"),
+            pib: String::from("Pio"),
+            eib: String::from("Eio"),
+        };
+        assert_eq!(HumanSize::from_bytes(1).format(&labels), "1 octet");
+        assert_eq!(HumanSize::from_bytes(2).format(&labels), "2 octets");
+        assert_eq!(HumanSize::from_bytes(2048).format(&labels), "2.0 Kio");
+    }
+}
@@ -0,0 +1,71 @@
+//! ## Symlink chain
+//!
+//! the chain of symbolic links leading from a path to its final target
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Chain of symbolic links starting at a path, as returned by [`crate::RemoteFs::resolve_chain`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SymlinkChain {
+    /// Each step of the chain, from the starting path to the last path reached (e.g. `[a, b, c]` for `a -> b -> c`)
+    pub steps: Vec<PathBuf>,
+    /// The step which doesn't exist, if the chain is broken. It is always the last of `steps`
+    pub broken_at: Option<PathBuf>,
+}
+
+impl SymlinkChain {
+    /// Returns whether the chain is broken, because one of its steps doesn't exist
+    pub fn is_broken(&self) -> bool {
+        self.broken_at.is_some()
+    }
+
+    /// Get the final target of the chain, which is not a symlink, if the chain is not broken
+    pub fn target(&self) -> Option<&Path> {
+        match self.broken_at {
+            Some(_) => None,
+            None => self.steps.last().map(PathBuf::as_path),
+        }
+    }
+}
+
+impl fmt::Display for SymlinkChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| step.display().to_string())
+            .collect();
+        write!(f, "{}", steps.join(" -> "))?;
+        if let Some(missing) = &self.broken_at {
+            write!(f, " ({} does not exist)", missing.display())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_describe_symlink_chain() {
+        let chain = SymlinkChain {
+            steps: vec![PathBuf::from("/a"), PathBuf::from("/b")],
+            broken_at: None,
+        };
+        assert!(!chain.is_broken());
+        assert_eq!(chain.target(), Some(Path::new("/b")));
+        assert_eq!(chain.to_string(), "/a -> /b");
+        let chain = SymlinkChain {
+            broken_at: Some(PathBuf::from("/b")),
+            ..chain
+        };
+        assert!(chain.is_broken());
+        assert_eq!(chain.target(), None);
+        assert_eq!(chain.to_string(), "/a -> /b (/b does not exist)");
+    }
+}
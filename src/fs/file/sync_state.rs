@@ -0,0 +1,172 @@
+//! ## Sync state
+//!
+//! relationship between a remote entry and the corresponding local file
+
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use super::File;
+
+/// Relationship between a remote entry and the corresponding local file, as classified by [`SyncState::between`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SyncState {
+    /// Both exist and have the same size and modify time
+    InSync,
+    /// Both exist and the remote entry has been modified more recently
+    RemoteNewer,
+    /// Both exist and the local file has been modified more recently
+    LocalNewer,
+    /// Only the remote entry exists
+    RemoteOnly,
+    /// Only the local file exists
+    LocalOnly,
+    /// Both exist, but which one is newer can't be told: they have the same modify time but different sizes,
+    /// a modify time is unknown, or one is a directory while the other isn't
+    Conflict,
+}
+
+impl SyncState {
+    /// Default tolerance on modify times, covering the 2 seconds resolution of FAT file systems
+    /// and small clock skews between the local host and the server
+    pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(2);
+
+    /// Classify the relationship between the `remote` entry, if it exists, and the `local` file.
+    ///
+    /// Files are compared by size and modify time; modify times closer than `tolerance` are considered equal,
+    /// so that clock skews and different time resolutions don't make files look modified.
+    /// Directories are in sync whenever both sides are directories.
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if neither exists, or any error stating `local`
+    pub fn between(remote: Option<&File>, local: &Path, tolerance: Duration) -> io::Result<Self> {
+        let local = match std::fs::metadata(local) {
+            Ok(metadata) => Some(metadata),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        let (remote, local) = match (remote, local) {
+            (Some(remote), Some(local)) => (remote, local),
+            (Some(_), None) => return Ok(Self::RemoteOnly),
+            (None, Some(_)) => return Ok(Self::LocalOnly),
+            (None, None) => return Err(io::Error::from(io::ErrorKind::NotFound)),
+        };
+        match (remote.is_dir(), local.is_dir()) {
+            (true, true) => return Ok(Self::InSync),
+            (true, false) | (false, true) => return Ok(Self::Conflict),
+            (false, false) => {}
+        }
+        let same_size = remote.metadata().size == local.len();
+        let (remote_mtime, local_mtime) = match (remote.metadata().modified, local.modified().ok())
+        {
+            (Some(remote), Some(local)) => (remote, local),
+            _ if same_size => return Ok(Self::InSync),
+            _ => return Ok(Self::Conflict),
+        };
+        Ok(match Self::newer(remote_mtime, local_mtime, tolerance) {
+            Some(true) => Self::RemoteNewer,
+            Some(false) => Self::LocalNewer,
+            None if same_size => Self::InSync,
+            None => Self::Conflict,
+        })
+    }
+
+    /// Returns whether `remote` is newer than `local`, or `None` if they're within `tolerance`
+    fn newer(remote: SystemTime, local: SystemTime, tolerance: Duration) -> Option<bool> {
+        match remote.duration_since(local) {
+            Ok(diff) if diff > tolerance => Some(true),
+            Ok(_) => None,
+            Err(err) if err.duration() > tolerance => Some(false),
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::fs::{FileType, Metadata};
+
+    fn local_file(dir: &TempDir, name: &str, content: &[u8], modified: SystemTime) -> PathBuf {
+        let path = dir.path().join(name);
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(content).unwrap();
+        file.set_modified(modified).unwrap();
+        path
+    }
+
+    fn remote_file(size: u64, modified: SystemTime) -> File {
+        File {
+            path: PathBuf::from("/a.txt"),
+            metadata: Metadata::default().size(size).modified(modified),
+        }
+    }
+
+    #[test]
+    fn should_classify_sync_state() {
+        let dir = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        let hour = Duration::from_secs(3600);
+        let local = local_file(&dir, "a.txt", b"hello", now);
+        let state = |remote: &File| remote.compare_with_local(&local).unwrap();
+        assert_eq!(state(&remote_file(5, now)), SyncState::InSync);
+        // clock skew within tolerance
+        assert_eq!(
+            state(&remote_file(5, now + Duration::from_secs(1))),
+            SyncState::InSync
+        );
+        assert_eq!(state(&remote_file(7, now + hour)), SyncState::RemoteNewer);
+        assert_eq!(state(&remote_file(7, now - hour)), SyncState::LocalNewer);
+        assert_eq!(state(&remote_file(7, now)), SyncState::Conflict);
+        assert_eq!(
+            state(&File {
+                path: PathBuf::from("/a.txt"),
+                metadata: Metadata::default().file_type(FileType::Directory),
+            }),
+            SyncState::Conflict
+        );
+        assert_eq!(
+            remote_file(5, now)
+                .compare_with_local(&dir.path().join("missing.txt"))
+                .unwrap(),
+            SyncState::RemoteOnly
+        );
+        assert_eq!(
+            SyncState::between(None, &local, SyncState::DEFAULT_TOLERANCE).unwrap(),
+            SyncState::LocalOnly
+        );
+        assert_eq!(
+            SyncState::between(
+                None,
+                &dir.path().join("missing.txt"),
+                SyncState::DEFAULT_TOLERANCE
+            )
+            .unwrap_err()
+            .kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn should_classify_sync_state_with_tolerance() {
+        let dir = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        let local = local_file(&dir, "a.txt", b"hello", now);
+        let remote = remote_file(5, now + Duration::from_secs(10));
+        assert_eq!(
+            remote.compare_with_local(&local).unwrap(),
+            SyncState::RemoteNewer
+        );
+        assert_eq!(
+            remote
+                .compare_with_local_within(&local, Duration::from_secs(60))
+                .unwrap(),
+            SyncState::InSync
+        );
+    }
+}
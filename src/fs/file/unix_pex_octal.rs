@@ -0,0 +1,118 @@
+//! ## Unix pex octal
+//!
+//! (de)serialize [`UnixPex`] as an octal string, such as `"0755"`.
+//!
+//! Use it with `#[serde(with = "remotefs::fs::unix_pex_octal")]`,
+//! or with `#[serde(with = "remotefs::fs::unix_pex_octal::option")]` for `Option<UnixPex>`.
+
+use std::fmt;
+
+use serde_core::de::{Error, Visitor};
+use serde_core::{Deserializer, Serializer};
+
+use super::{ParseUnixPexError, UnixPex};
+
+/// Serialize `pex` as an octal string, such as `"0755"`
+pub fn serialize<S: Serializer>(pex: &UnixPex, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_octal(pex))
+}
+
+/// Deserialize a [`UnixPex`] from an octal string, such as `"0755"`, `"755"` or `"0o755"`
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<UnixPex, D::Error> {
+    deserializer.deserialize_str(OctalVisitor)
+}
+
+/// (de)serialize `Option<UnixPex>` as an optional octal string
+pub mod option {
+
+    use serde_core::{Deserialize, Deserializer, Serializer};
+
+    use super::{from_octal, to_octal, UnixPex};
+
+    /// Serialize `pex` as an optional octal string
+    pub fn serialize<S: Serializer>(
+        pex: &Option<UnixPex>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match pex {
+            Some(pex) => serializer.serialize_some(&to_octal(pex)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserialize an optional octal string into `Option<UnixPex>`
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<UnixPex>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => from_octal(&s)
+                .map(Some)
+                .map_err(serde_core::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Format `pex` as a 4-digit octal string
+fn to_octal(pex: &UnixPex) -> String {
+    format!("{:04o}", u32::from(*pex))
+}
+
+/// Parse an octal string into [`UnixPex`]
+fn from_octal(s: &str) -> Result<UnixPex, ParseUnixPexError> {
+    UnixPex::parse_octal(s)
+}
+
+struct OctalVisitor;
+
+impl Visitor<'_> for OctalVisitor {
+    type Value = UnixPex;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an octal permissions string, such as \"0755\"")
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        from_octal(v).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+    use serde_core::de::value::{Error as ValueError, StrDeserializer};
+    use serde_core::de::IntoDeserializer;
+
+    use super::*;
+
+    fn parse(s: &str) -> Result<UnixPex, ValueError> {
+        let deserializer: StrDeserializer<ValueError> = s.into_deserializer();
+        deserialize(deserializer)
+    }
+
+    #[test]
+    fn should_round_trip_unix_pex_as_octal_string() {
+        for mode in [0o000, 0o600, 0o644, 0o700, 0o755, 0o777] {
+            let pex = UnixPex::from(mode);
+            let s = to_octal(&pex);
+            assert_eq!(parse(&s).unwrap(), pex, "{}", s);
+        }
+        assert_eq!(to_octal(&UnixPex::from(0o755)), "0755");
+        assert_eq!(to_octal(&UnixPex::from(0o640)), "0640");
+    }
+
+    #[test]
+    fn should_deserialize_unix_pex_without_leading_zero() {
+        assert_eq!(parse("755").unwrap(), UnixPex::from(0o755));
+        assert_eq!(parse("0o644").unwrap(), UnixPex::from(0o644));
+    }
+
+    #[test]
+    fn should_not_deserialize_invalid_unix_pex() {
+        assert!(parse("").is_err());
+        assert!(parse("0789").is_err());
+        assert!(parse("rwxr-xr-x").is_err());
+        assert!(parse("17777").is_err());
+    }
+}
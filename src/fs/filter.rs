@@ -0,0 +1,92 @@
+//! ## Filter
+//!
+//! filters on file entries
+
+use super::File;
+
+/// A filter on file entries, used by [`crate::RemoteFs::find_filtered`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EntryFilter {
+    /// Matches regular files with size `0` and directories with no entries
+    IsEmpty,
+    /// Matches entries owned by the user with this uid
+    OwnedBy(u32),
+    /// Matches entries owned by the group with this gid
+    GroupIs(u32),
+}
+
+impl EntryFilter {
+    /// Returns whether `entry` matches the filter.
+    ///
+    /// Returns `None` if it can't be told from the metadata of `entry`,
+    /// such as whether a directory is empty when [`crate::fs::Metadata::child_count`] is unknown,
+    /// or the owner of an entry whose uid or gid is unknown.
+    pub fn matches(&self, entry: &File) -> Option<bool> {
+        match self {
+            Self::IsEmpty if entry.is_dir() => entry.child_count().map(|count| count == 0),
+            Self::IsEmpty => Some(entry.is_file() && entry.metadata().size == 0),
+            Self::OwnedBy(uid) => entry.metadata().uid.map(|owner| owner == *uid),
+            Self::GroupIs(gid) => entry.metadata().gid.map(|group| group == *gid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::fs::{FileType, Metadata};
+
+    fn entry(metadata: Metadata) -> File {
+        File {
+            path: PathBuf::from("/a"),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn should_match_empty_entries() {
+        let filter = EntryFilter::IsEmpty;
+        assert_eq!(filter.matches(&entry(Metadata::default())), Some(true));
+        assert_eq!(
+            filter.matches(&entry(Metadata::default().size(12))),
+            Some(false)
+        );
+        assert_eq!(
+            filter.matches(&entry(Metadata::default().file_type(FileType::Symlink))),
+            Some(false)
+        );
+        let dir = Metadata::default().file_type(FileType::Directory);
+        assert_eq!(filter.matches(&entry(dir.clone())), None);
+        assert_eq!(
+            filter.matches(&entry(dir.clone().child_count(0))),
+            Some(true)
+        );
+        assert_eq!(filter.matches(&entry(dir.child_count(3))), Some(false));
+    }
+
+    #[test]
+    fn should_match_entries_by_owner_and_group() {
+        let listing = [
+            entry(Metadata::default().uid(1001).gid(100)),
+            entry(Metadata::default().uid(1001).gid(1001)),
+            entry(Metadata::default().uid(0).gid(100)),
+            entry(Metadata::default()),
+        ];
+        let matches = |filter: EntryFilter| -> Vec<Option<bool>> {
+            listing.iter().map(|entry| filter.matches(entry)).collect()
+        };
+        assert_eq!(
+            matches(EntryFilter::OwnedBy(1001)),
+            vec![Some(true), Some(true), Some(false), None]
+        );
+        assert_eq!(
+            matches(EntryFilter::GroupIs(100)),
+            vec![Some(true), Some(false), Some(true), None]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! ## Host key
+//!
+//! host key verification for protocols authenticating the server, such as SSH
+
+use crate::utils::digest::Sha256;
+
+/// The public key presented by the remote host while connecting
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HostKey {
+    /// Host name (or address) of the remote server
+    pub host: String,
+    /// Key algorithm, such as `ssh-ed25519`
+    pub algorithm: String,
+    /// Raw public key
+    pub key: Vec<u8>,
+}
+
+impl HostKey {
+    /// Instantiates a new `HostKey`
+    pub fn new<S: ToString, A: ToString>(host: S, algorithm: A, key: Vec<u8>) -> Self {
+        Self {
+            host: host.to_string(),
+            algorithm: algorithm.to_string(),
+            key,
+        }
+    }
+
+    /// Get the SHA-256 fingerprint of the key, hex-encoded
+    pub fn fingerprint(&self) -> String {
+        let mut digest = Sha256::default();
+        digest.update(&self.key);
+        digest.finalize()
+    }
+}
+
+/// Decision taken by a [`HostKeyVerifier`] on the key presented by the remote host
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HostKeyDecision {
+    /// Trust the key for this connection only
+    Accept,
+    /// Don't trust the key; the connection fails with [`crate::RemoteErrorType::HostKeyMismatch`]
+    Reject,
+    /// Trust the key and store it (e.g. in `known_hosts`), so it's trusted for the next connections
+    AcceptAndStore,
+}
+
+impl HostKeyDecision {
+    /// Returns whether the key is trusted
+    pub fn is_accepted(&self) -> bool {
+        !matches!(self, Self::Reject)
+    }
+}
+
+/// Callback verifying the key presented by the remote host while connecting,
+/// set with [`crate::RemoteFs::set_host_key_verifier`].
+/// Use it to check the key against `known_hosts` or to prompt the user
+pub type HostKeyVerifier = Box<dyn FnMut(&HostKey) -> HostKeyDecision + Send>;
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_create_host_key() {
+        let key = HostKey::new("example.com", "ssh-ed25519", b"abc".to_vec());
+        assert_eq!(key.host, "example.com");
+        assert_eq!(key.algorithm, "ssh-ed25519");
+        assert_eq!(
+            key.fingerprint(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(HostKeyDecision::Accept.is_accepted());
+        assert!(HostKeyDecision::AcceptAndStore.is_accepted());
+        assert!(!HostKeyDecision::Reject.is_accepted());
+    }
+}
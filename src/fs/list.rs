@@ -0,0 +1,118 @@
+//! ## List
+//!
+//! options for listing directories and summaries of listings
+
+use super::File;
+
+/// Defines the order of the entries returned by [`crate::RemoteFs::list_dir_with`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ListOrder {
+    /// Entries are sorted by name
+    #[default]
+    Name,
+    /// Entries are returned in the order provided by the remote server, which may be arbitrary
+    Unsorted,
+}
+
+/// Options for [`crate::RemoteFs::list_dir_with`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListOptions {
+    /// Order of the returned entries
+    pub order: ListOrder,
+    /// Whether to populate the target ([`crate::fs::Metadata::symlink`]) of the symlinks
+    /// whose target isn't reported by the listing, reading them with [`crate::RemoteFs::read_links`]
+    pub resolve_symlinks: bool,
+}
+
+impl ListOptions {
+    /// Construct options with the order of entries
+    pub fn order(mut self, order: ListOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Construct options resolving the target of symlinks
+    pub fn resolve_symlinks(mut self, resolve: bool) -> Self {
+        self.resolve_symlinks = resolve;
+        self
+    }
+}
+
+/// Summary of a directory listing, as returned by [`crate::RemoteFs::list_dir_summary`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DirSummary {
+    /// Amount of entries which are not directories (regular files and symlinks)
+    pub files: usize,
+    /// Amount of directories
+    pub dirs: usize,
+    /// Sum of the sizes of the entries which are not directories, in bytes
+    pub total_size: u64,
+}
+
+impl DirSummary {
+    /// Summarize `entries`
+    pub fn from_entries(entries: &[File]) -> Self {
+        entries.iter().fold(Self::default(), |mut summary, entry| {
+            if entry.is_dir() {
+                summary.dirs += 1;
+            } else {
+                summary.files += 1;
+                summary.total_size += entry.metadata().size;
+            }
+            summary
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::fs::{FileType, Metadata};
+
+    #[test]
+    fn should_create_list_options() {
+        assert_eq!(ListOptions::default().order, ListOrder::Name);
+        assert_eq!(
+            ListOptions::default().order(ListOrder::Unsorted).order,
+            ListOrder::Unsorted
+        );
+        assert!(!ListOptions::default().resolve_symlinks);
+        assert!(
+            ListOptions::default()
+                .resolve_symlinks(true)
+                .resolve_symlinks
+        );
+    }
+
+    #[test]
+    fn should_summarize_entries() {
+        let entry = |path: &str, metadata: Metadata| File {
+            path: PathBuf::from(path),
+            metadata,
+        };
+        let entries = [
+            entry("/a.txt", Metadata::default().size(1024)),
+            entry("/b.txt", Metadata::default().size(24)),
+            entry(
+                "/c",
+                Metadata::default()
+                    .file_type(FileType::Directory)
+                    .size(4096),
+            ),
+        ];
+        assert_eq!(
+            DirSummary::from_entries(&entries),
+            DirSummary {
+                files: 2,
+                dirs: 1,
+                total_size: 1048,
+            }
+        );
+        assert_eq!(DirSummary::from_entries(&[]), DirSummary::default());
+    }
+}
@@ -0,0 +1,452 @@
+//! ## Metered
+//!
+//! a `RemoteFs` wrapper which collects metrics about the operations performed
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::stream::{ReadAndSeek, WriteAndSeek};
+use super::{
+    ExecOutput, File, HostKeyVerifier, Metadata, ReadStream, RemoteFs, RemoteResult, UnixPex,
+    Welcome, WriteStream,
+};
+
+/// Metrics collected for a single kind of operation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OperationMetrics {
+    /// Amount of times the operation has been called
+    pub count: u64,
+    /// Amount of times the operation has failed
+    pub errors: u64,
+    /// Total time spent in the operation
+    pub duration: Duration,
+}
+
+/// Metrics collected by [`MeteredFs`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FsMetrics {
+    /// Metrics for each operation, by method name (e.g. `stat`)
+    pub operations: HashMap<&'static str, OperationMetrics>,
+    /// Bytes read from the remote server
+    pub bytes_read: u64,
+    /// Bytes written to the remote server
+    pub bytes_written: u64,
+}
+
+impl FsMetrics {
+    /// Get metrics for operation `name`
+    pub fn operation(&self, name: &str) -> OperationMetrics {
+        self.operations.get(name).copied().unwrap_or_default()
+    }
+
+    /// Get the total amount of operations performed
+    pub fn total_operations(&self) -> u64 {
+        self.operations.values().map(|op| op.count).sum()
+    }
+}
+
+/// A [`RemoteFs`] wrapper which records per-operation counts, durations and transferred bytes into [`FsMetrics`].
+///
+/// Bytes are counted both for the blocking methods (e.g. [`RemoteFs::create_file`])
+/// and for the streams returned by [`RemoteFs::open`], [`RemoteFs::create`] and [`RemoteFs::append`].
+/// The duration of a stream operation only covers opening the stream.
+///
+/// Methods composed of other operations (e.g. [`RemoteFs::set_times`]) are run on the wrapper,
+/// so each underlying operation is recorded.
+pub struct MeteredFs<T: RemoteFs> {
+    inner: T,
+    metrics: FsMetrics,
+    bytes_read: Arc<AtomicU64>,
+    bytes_written: Arc<AtomicU64>,
+}
+
+impl<T: RemoteFs> MeteredFs<T> {
+    /// Wrap `inner` into a new `MeteredFs`
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            metrics: FsMetrics::default(),
+            bytes_read: Arc::default(),
+            bytes_written: Arc::default(),
+        }
+    }
+
+    /// Get a snapshot of the metrics collected so far
+    pub fn metrics(&self) -> FsMetrics {
+        let mut metrics = self.metrics.clone();
+        metrics.bytes_read = self.bytes_read.load(Ordering::Relaxed);
+        metrics.bytes_written = self.bytes_written.load(Ordering::Relaxed);
+        metrics
+    }
+
+    /// Reset metrics collected so far
+    pub fn reset(&mut self) {
+        self.metrics = FsMetrics::default();
+        self.bytes_read.store(0, Ordering::Relaxed);
+        self.bytes_written.store(0, Ordering::Relaxed);
+    }
+
+    /// Get a reference to the inner file system
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner file system
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner file system
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Run `f` on the inner file system, recording it as operation `name`
+    fn record<R, F>(&mut self, name: &'static str, f: F) -> RemoteResult<R>
+    where
+        F: FnOnce(&mut T) -> RemoteResult<R>,
+    {
+        let started = Instant::now();
+        let result = f(&mut self.inner);
+        let op = self.metrics.operations.entry(name).or_default();
+        op.count += 1;
+        op.duration += started.elapsed();
+        if result.is_err() {
+            op.errors += 1;
+        }
+        result
+    }
+
+    /// Run `f` on the inner file system, recording it as a single operation `name`,
+    /// which has failed if any of the results is an error
+    fn record_many<R, F>(&mut self, name: &'static str, f: F) -> Vec<RemoteResult<R>>
+    where
+        F: FnOnce(&mut T) -> Vec<RemoteResult<R>>,
+    {
+        let started = Instant::now();
+        let results = f(&mut self.inner);
+        let op = self.metrics.operations.entry(name).or_default();
+        op.count += 1;
+        op.duration += started.elapsed();
+        if results.iter().any(Result::is_err) {
+            op.errors += 1;
+        }
+        results
+    }
+
+    fn count_written(&self, stream: WriteStream) -> WriteStream {
+        let counter = self.bytes_written.clone();
+        stream.wrap(|stream| Counting { stream, counter })
+    }
+
+    fn count_read(&self, stream: ReadStream) -> ReadStream {
+        let counter = self.bytes_read.clone();
+        stream.wrap(|stream| Counting { stream, counter })
+    }
+}
+
+impl<T: RemoteFs> RemoteFs for MeteredFs<T> {
+    fn connect(&mut self) -> RemoteResult<Welcome> {
+        self.record("connect", |fs| fs.connect())
+    }
+
+    fn set_host_key_verifier(&mut self, verifier: HostKeyVerifier) {
+        self.inner.set_host_key_verifier(verifier)
+    }
+
+    fn disconnect(&mut self) -> RemoteResult<()> {
+        self.record("disconnect", |fs| fs.disconnect())
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn pwd(&mut self) -> RemoteResult<PathBuf> {
+        self.record("pwd", |fs| fs.pwd())
+    }
+
+    fn change_dir(&mut self, dir: &Path) -> RemoteResult<PathBuf> {
+        self.record("change_dir", |fs| fs.change_dir(dir))
+    }
+
+    fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>> {
+        self.record("list_dir", |fs| fs.list_dir(path))
+    }
+
+    fn stat(&mut self, path: &Path) -> RemoteResult<File> {
+        self.record("stat", |fs| fs.stat(path))
+    }
+
+    fn stat_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<File>> {
+        self.record_many("stat_many", |fs| fs.stat_many(paths))
+    }
+
+    fn setstat(&mut self, path: &Path, metadata: Metadata) -> RemoteResult<()> {
+        self.record("setstat", |fs| fs.setstat(path, metadata))
+    }
+
+    fn exists(&mut self, path: &Path) -> RemoteResult<bool> {
+        self.record("exists", |fs| fs.exists(path))
+    }
+
+    fn exists_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<bool>> {
+        self.record_many("exists_many", |fs| fs.exists_many(paths))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> RemoteResult<()> {
+        self.record("remove_file", |fs| fs.remove_file(path))
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> RemoteResult<()> {
+        self.record("remove_dir", |fs| fs.remove_dir(path))
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> RemoteResult<()> {
+        self.record("remove_dir_all", |fs| fs.remove_dir_all(path))
+    }
+
+    fn create_dir(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()> {
+        self.record("create_dir", |fs| fs.create_dir(path, mode))
+    }
+
+    fn read_link(&mut self, path: &Path) -> RemoteResult<PathBuf> {
+        self.record("read_link", |fs| fs.read_link(path))
+    }
+
+    fn read_links(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<PathBuf>> {
+        self.record_many("read_links", |fs| fs.read_links(paths))
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> RemoteResult<()> {
+        self.record("symlink", |fs| fs.symlink(path, target))
+    }
+
+    fn copy(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.record("copy", |fs| fs.copy(src, dest))
+    }
+
+    fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.record("mov", |fs| fs.mov(src, dest))
+    }
+
+    fn exec(&mut self, cmd: &str) -> RemoteResult<(u32, String)> {
+        self.record("exec", |fs| fs.exec(cmd))
+    }
+
+    fn exec_with_stdin(
+        &mut self,
+        cmd: &str,
+        stdin: Box<dyn Read + Send>,
+    ) -> RemoteResult<ExecOutput> {
+        let output = self.record("exec_with_stdin", |fs| fs.exec_with_stdin(cmd, stdin))?;
+        self.bytes_written
+            .fetch_add(output.stdin_written, Ordering::Relaxed);
+        Ok(output)
+    }
+
+    fn append(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        let stream = self.record("append", |fs| fs.append(path, metadata))?;
+        Ok(self.count_written(stream))
+    }
+
+    fn create(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        let stream = self.record("create", |fs| fs.create(path, metadata))?;
+        Ok(self.count_written(stream))
+    }
+
+    fn open(&mut self, path: &Path) -> RemoteResult<ReadStream> {
+        let stream = self.record("open", |fs| fs.open(path))?;
+        Ok(self.count_read(stream))
+    }
+
+    fn open_range(
+        &mut self,
+        path: &Path,
+        offset: u64,
+        len: Option<u64>,
+    ) -> RemoteResult<ReadStream> {
+        let stream = self.record("open_range", |fs| fs.open_range(path, offset, len))?;
+        Ok(self.count_read(stream))
+    }
+
+    fn open_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<ReadStream> {
+        let stream = self.record("open_named_stream", |fs| fs.open_named_stream(path, name))?;
+        Ok(self.count_read(stream))
+    }
+
+    fn create_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<WriteStream> {
+        let stream = self.record("create_named_stream", |fs| {
+            fs.create_named_stream(path, name)
+        })?;
+        Ok(self.count_written(stream))
+    }
+
+    fn on_written(&mut self, writable: WriteStream) -> RemoteResult<()> {
+        self.record("on_written", |fs| fs.on_written(writable))
+    }
+
+    fn on_read(&mut self, readable: ReadStream) -> RemoteResult<()> {
+        self.record("on_read", |fs| fs.on_read(readable))
+    }
+
+    fn buffer_size(&self) -> usize {
+        self.inner.buffer_size()
+    }
+
+    fn last_working_dir(&self) -> Option<PathBuf> {
+        self.inner.last_working_dir()
+    }
+
+    fn fsync(&mut self, path: &Path) -> RemoteResult<()> {
+        self.record("fsync", |fs| fs.fsync(path))
+    }
+
+    fn append_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        let sz = self.record("append_file", |fs| fs.append_file(path, metadata, reader))?;
+        self.bytes_written.fetch_add(sz, Ordering::Relaxed);
+        Ok(sz)
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        let sz = self.record("create_file", |fs| fs.create_file(path, metadata, reader))?;
+        self.bytes_written.fetch_add(sz, Ordering::Relaxed);
+        Ok(sz)
+    }
+
+    fn open_file(&mut self, src: &Path) -> RemoteResult<(u64, Vec<u8>)> {
+        let (sz, data) = self.record("open_file", |fs| fs.open_file(src))?;
+        self.bytes_read.fetch_add(sz, Ordering::Relaxed);
+        Ok((sz, data))
+    }
+
+    fn checksum(&mut self, path: &Path) -> RemoteResult<String> {
+        self.record("checksum", |fs| fs.checksum(path))
+    }
+
+    #[cfg(feature = "find")]
+    fn find(&mut self, search: &str) -> RemoteResult<Vec<File>> {
+        self.record("find", |fs| fs.find(search))
+    }
+}
+
+/// A stream which counts the bytes going through it
+struct Counting<S> {
+    stream: S,
+    counter: Arc<AtomicU64>,
+}
+
+impl Read for Counting<ReadStream> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.stream.read(buf)?;
+        self.counter.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl Write for Counting<WriteStream> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.stream.write(buf)?;
+        self.counter.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<S: Seek> Seek for Counting<S> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.stream.seek(pos)
+    }
+}
+
+impl ReadAndSeek for Counting<ReadStream> {}
+
+impl WriteAndSeek for Counting<WriteStream> {}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Cursor;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mock::MemoryFs;
+
+    #[test]
+    fn should_record_metrics() {
+        let mut client = MeteredFs::new(MemoryFs::new().with_file("/a.txt", b"hello"));
+        assert!(client.stat(Path::new("/a.txt")).is_ok());
+        assert!(client.stat(Path::new("/b.txt")).is_err());
+        assert!(client.exists(Path::new("/a.txt")).unwrap());
+        // stream
+        let mut stream = client.open(Path::new("/a.txt")).unwrap();
+        assert!(stream.seekable());
+        let mut buf = vec![];
+        stream.read_to_end(&mut buf).unwrap();
+        client.on_read(stream).unwrap();
+        let mut stream = client
+            .create(Path::new("/b.txt"), &Metadata::default())
+            .unwrap();
+        stream.write_all(b"hi").unwrap();
+        client.on_written(stream).unwrap();
+        // blocking
+        assert_eq!(
+            client
+                .create_file(
+                    Path::new("/c.txt"),
+                    &Metadata::default(),
+                    Box::new(Cursor::new(b"world!".to_vec())),
+                )
+                .unwrap(),
+            6
+        );
+        assert_eq!(client.open_file(Path::new("/c.txt")).unwrap().0, 6);
+
+        let metrics = client.metrics();
+        assert_eq!(
+            metrics.operation("stat"),
+            OperationMetrics {
+                count: 2,
+                errors: 1,
+                duration: metrics.operation("stat").duration,
+            }
+        );
+        assert_eq!(metrics.operation("exists").count, 1);
+        assert_eq!(metrics.operation("open").count, 1);
+        assert_eq!(metrics.operation("create").count, 1);
+        assert_eq!(metrics.operation("create_file").count, 1);
+        assert_eq!(metrics.operation("open_file").count, 1);
+        assert_eq!(metrics.operation("remove_file").count, 0);
+        assert_eq!(metrics.total_operations(), 9);
+        assert_eq!(metrics.bytes_read, 11);
+        assert_eq!(metrics.bytes_written, 8);
+    }
+
+    #[test]
+    fn should_reset_metrics() {
+        let mut client = MeteredFs::new(MemoryFs::new().with_file("/a.txt", b"hello"));
+        assert_eq!(client.open_file(Path::new("/a.txt")).unwrap().0, 5);
+        assert_eq!(client.metrics().bytes_read, 5);
+        client.reset();
+        assert_eq!(client.metrics(), FsMetrics::default());
+        assert_eq!(client.into_inner().content("/a.txt").unwrap(), b"hello");
+    }
+}
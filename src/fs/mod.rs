@@ -2,14 +2,45 @@
 //!
 //! `fs` is the module which provides remote file system entities
 
+mod cancel;
 mod errors;
+mod exec;
+mod fallback;
 mod file;
+mod filter;
+mod host_key;
+mod list;
+mod metered;
+mod random_access;
 pub mod stream;
 mod sync;
+#[cfg(feature = "trace-fs")]
+mod traced;
+mod tree;
+mod walk;
 mod welcome;
 
+pub use self::cancel::{CancellableFs, CancellationToken};
 pub use self::errors::{RemoteError, RemoteErrorType, RemoteResult};
-pub use self::file::{File, FileType, Metadata, UnixPex, UnixPexClass};
-pub use self::stream::{ReadStream, WriteStream};
-pub use self::sync::RemoteFs;
+pub use self::exec::ExecOutput;
+pub use self::fallback::{Fallback, FallbackFs};
+#[cfg(feature = "serde")]
+pub use self::file::unix_pex_octal;
+#[cfg(feature = "dir-entry")]
+pub use self::file::DirEntryLike;
+pub use self::file::{
+    FieldMask, File, FileType, HumanSize, InvalidDigit, Metadata, ParseUnixPexError, PathStatus,
+    SizeUnit, SymlinkChain, SyncState, UnitLabels, UnixPex, UnixPexClass,
+};
+pub use self::filter::EntryFilter;
+pub use self::host_key::{HostKey, HostKeyDecision, HostKeyVerifier};
+pub use self::list::{DirSummary, ListOptions, ListOrder};
+pub use self::metered::{FsMetrics, MeteredFs, OperationMetrics};
+pub use self::random_access::RandomAccessFile;
+pub use self::stream::{LineReader, ReadStream, WriteStream, DEFAULT_BUFFER_SIZE};
+pub use self::sync::{RemoteFs, MAX_PARALLEL_DOWNLOADS};
+#[cfg(feature = "trace-fs")]
+pub use self::traced::{TracingFs, TRACING_FS_TARGET};
+pub use self::tree::{build_tree, compare_trees, rewrite_prefix, TreeDiff, TreeNode};
+pub use self::walk::WalkOptions;
 pub use self::welcome::Welcome;
@@ -0,0 +1,183 @@
+//! ## Random access
+//!
+//! a handle to read a remote file at arbitrary offsets
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::{RemoteError, RemoteErrorType, RemoteFs, RemoteResult};
+
+/// Maximum amount of blocks kept in the cache of a [`RandomAccessFile`]
+const CACHE_BLOCKS: usize = 32;
+
+/// A handle to read a remote file at arbitrary offsets, returned by [`RemoteFs::open_random`].
+///
+/// The file is read in blocks of [`RemoteFs::buffer_size`] bytes with [`RemoteFs::open_range`].
+/// The most recently read blocks are cached, so reading again from a cached block doesn't reach the server.
+/// Changes made to the remote file after it has been opened may not be seen.
+pub struct RandomAccessFile<'a, T: RemoteFs + ?Sized> {
+    fs: &'a mut T,
+    path: PathBuf,
+    len: u64,
+    block_size: u64,
+    blocks: HashMap<u64, Vec<u8>>,
+    /// Cached block indexes, from the least to the most recently used
+    recent: VecDeque<u64>,
+}
+
+impl<'a, T: RemoteFs + ?Sized> RandomAccessFile<'a, T> {
+    /// Open the file at `path` on `fs` for random access
+    pub fn new(fs: &'a mut T, path: &Path) -> RemoteResult<Self> {
+        let file = fs.stat(path)?;
+        if !file.is_file() {
+            return Err(RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                format!("{} is not a file", path.display()),
+            ));
+        }
+        let block_size = fs.buffer_size().max(1) as u64;
+        Ok(Self {
+            fs,
+            path: file.path,
+            len: file.metadata.size,
+            block_size,
+            blocks: HashMap::new(),
+            recent: VecDeque::with_capacity(CACHE_BLOCKS),
+        })
+    }
+
+    /// Get the size of the file, as it was when the file was opened
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether the file is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read from the file at `offset` into `buf`, as `pread` does.
+    /// Returns the amount of bytes read, which is less than the length of `buf` only if the end of the file is reached
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> RemoteResult<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            let position = offset + read as u64;
+            if position >= self.len {
+                break;
+            }
+            let index = position / self.block_size;
+            let start = (position % self.block_size) as usize;
+            let block = self.block(index)?;
+            if start >= block.len() {
+                break;
+            }
+            let n = (block.len() - start).min(buf.len() - read);
+            buf[read..read + n].copy_from_slice(&block[start..start + n]);
+            read += n;
+        }
+        Ok(read)
+    }
+
+    /// Get the block at `index`, reading it from the server if it's not cached
+    fn block(&mut self, index: u64) -> RemoteResult<&[u8]> {
+        if self.blocks.contains_key(&index) {
+            self.recent.retain(|cached| *cached != index);
+        } else {
+            trace!("reading block {} of {}", index, self.path.display());
+            let mut stream = self.fs.open_range(
+                self.path.as_path(),
+                index * self.block_size,
+                Some(self.block_size),
+            )?;
+            let mut block = Vec::new();
+            (&mut stream)
+                .take(self.block_size)
+                .read_to_end(&mut block)
+                .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+            self.fs.on_read(stream)?;
+            if self.recent.len() == CACHE_BLOCKS {
+                if let Some(evicted) = self.recent.pop_front() {
+                    self.blocks.remove(&evicted);
+                }
+            }
+            self.blocks.insert(index, block);
+        }
+        self.recent.push_back(index);
+        Ok(self.blocks[&index].as_slice())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::fs::MeteredFs;
+    use crate::mock::MemoryFs;
+
+    #[test]
+    fn should_read_at_offsets_with_block_cache() {
+        let content: Vec<u8> = (0..40).collect();
+        let mut client = MeteredFs::new(
+            MemoryFs::new()
+                .with_file("/a.bin", &content)
+                .with_buffer_size(8),
+        );
+        let mut file = client.open_random(Path::new("/a.bin")).unwrap();
+        assert_eq!(file.len(), 40);
+        assert!(!file.is_empty());
+        let mut buf = [0; 4];
+        assert_eq!(file.read_at(10, &mut buf).unwrap(), 4);
+        assert_eq!(buf, [10, 11, 12, 13]);
+        // spans blocks 0 and 1
+        let mut buf = [0; 6];
+        assert_eq!(file.read_at(5, &mut buf).unwrap(), 6);
+        assert_eq!(buf, [5, 6, 7, 8, 9, 10]);
+        // at the end of the file
+        let mut buf = [0; 8];
+        assert_eq!(file.read_at(36, &mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], &[36, 37, 38, 39]);
+        assert_eq!(file.read_at(40, &mut buf).unwrap(), 0);
+        // cached
+        let mut buf = [0; 2];
+        assert_eq!(file.read_at(14, &mut buf).unwrap(), 2);
+        assert_eq!(buf, [14, 15]);
+        assert_eq!(client.metrics().operation("open_range").count, 3);
+    }
+
+    #[test]
+    fn should_evict_least_recently_used_blocks() {
+        let content = vec![0; CACHE_BLOCKS + 1];
+        let mut client = MeteredFs::new(
+            MemoryFs::new()
+                .with_file("/a.bin", &content)
+                .with_buffer_size(1),
+        );
+        let mut file = RandomAccessFile::new(&mut client, Path::new("/a.bin")).unwrap();
+        let mut buf = [0; 1];
+        for offset in 0..=CACHE_BLOCKS as u64 {
+            file.read_at(offset, &mut buf).unwrap();
+        }
+        // block 0 has been evicted, block 1 is still cached
+        file.read_at(1, &mut buf).unwrap();
+        file.read_at(0, &mut buf).unwrap();
+        assert_eq!(
+            client.metrics().operation("open_range").count,
+            CACHE_BLOCKS as u64 + 2
+        );
+    }
+
+    #[test]
+    fn should_not_open_directory_for_random_access() {
+        let mut client = MemoryFs::new().with_dir("/a");
+        assert_eq!(
+            RandomAccessFile::new(&mut client, Path::new("/a"))
+                .err()
+                .unwrap()
+                .kind,
+            RemoteErrorType::BadFile
+        );
+    }
+}
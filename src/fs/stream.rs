@@ -2,7 +2,14 @@
 //!
 //! this module exposes the streams returned by create, append and open methods
 
-use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read, Seek, Write};
+use std::io::{
+    BufRead, BufReader, BufWriter, Error as IoError, ErrorKind as IoErrorKind, Read, Seek, Write,
+};
+
+use crate::{RemoteError, RemoteErrorType, RemoteResult};
+
+/// Default size of the buffer used to transfer data, returned by [`crate::RemoteFs::buffer_size`]
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
 
 // -- read stream
 
@@ -12,6 +19,7 @@ pub trait ReadAndSeek: Read + Seek + Send {}
 /// The stream returned by [`crate::RemoteFs`] to read a file from the remote server
 pub struct ReadStream {
     stream: StreamReader,
+    bytes_read: u64,
 }
 
 /// The kind of stream contained in the stream. Can be [`Read`] only or [`Read`] + [`Seek`]
@@ -25,12 +33,37 @@ impl ReadStream {
     pub fn seekable(&self) -> bool {
         matches!(self.stream, StreamReader::ReadAndSeek(_))
     }
+
+    /// Returns the amount of bytes read from the stream so far.
+    /// Seeking the stream doesn't change this value.
+    ///
+    /// If the stream breaks during a transfer, the transfer can be resumed opening the file again
+    /// with [`crate::RemoteFs::open_range`], starting at the offset given by this value.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Wrap this stream into the stream returned by `f`, preserving whether the stream is seekable
+    pub(crate) fn wrap<W, F>(self, f: F) -> Self
+    where
+        W: ReadAndSeek + 'static,
+        F: FnOnce(Self) -> W,
+    {
+        if self.seekable() {
+            let reader: Box<dyn ReadAndSeek> = Box::new(f(self));
+            Self::from(reader)
+        } else {
+            let reader: Box<dyn Read + Send> = Box::new(f(self));
+            Self::from(reader)
+        }
+    }
 }
 
 impl From<Box<dyn Read + Send>> for ReadStream {
     fn from(reader: Box<dyn Read + Send>) -> Self {
         Self {
             stream: StreamReader::Read(reader),
+            bytes_read: 0,
         }
     }
 }
@@ -39,13 +72,18 @@ impl From<Box<dyn ReadAndSeek>> for ReadStream {
     fn from(reader: Box<dyn ReadAndSeek>) -> Self {
         Self {
             stream: StreamReader::ReadAndSeek(reader),
+            bytes_read: 0,
         }
     }
 }
 
+impl ReadAndSeek for BufReader<ReadStream> {}
+
 impl Read for ReadStream {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        self.stream.read(buf)
+        let n = self.stream.read(buf)?;
+        self.bytes_read += n as u64;
+        Ok(n)
     }
 }
 
@@ -76,6 +114,70 @@ impl Seek for StreamReader {
     }
 }
 
+// -- line reader
+
+/// An iterator over the lines of a [`ReadStream`], returned by [`crate::RemoteFs::read_lines`].
+///
+/// Lines are read one at a time, so the file is never loaded in memory at once.
+/// Line terminators (`\n` or `\r\n`) are not included in the lines.
+/// Lines which are not valid UTF-8 yield a [`RemoteErrorType::BadFile`] error, and iteration goes on with the next line.
+/// Once the stream fails, the iterator yields a [`RemoteErrorType::IoError`] error and then ends.
+///
+/// Once done, finalize the stream with [`crate::RemoteFs::on_read`], getting it with [`LineReader::into_inner`].
+pub struct LineReader {
+    reader: BufReader<ReadStream>,
+    line: usize,
+    failed: bool,
+}
+
+impl LineReader {
+    /// Instantiates a new `LineReader` reading lines from `stream`
+    pub fn new(stream: ReadStream) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+            line: 0,
+            failed: false,
+        }
+    }
+
+    /// Unwrap the inner stream. Data buffered and not yet returned as lines is lost
+    pub fn into_inner(self) -> ReadStream {
+        self.reader.into_inner()
+    }
+}
+
+impl Iterator for LineReader {
+    type Item = RemoteResult<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let mut buf = Vec::new();
+        match self.reader.read_until(b'\n', &mut buf) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(err) => {
+                self.failed = true;
+                return Some(Err(RemoteError::new_ex(RemoteErrorType::IoError, err)));
+            }
+        }
+        self.line += 1;
+        if buf.ends_with(b"\n") {
+            buf.pop();
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        }
+        Some(String::from_utf8(buf).map_err(|_| {
+            RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                format!("line {} is not valid UTF-8", self.line),
+            )
+        }))
+    }
+}
+
 // -- write stream
 
 /// A trait which combines `io::Write` and `io::Seek` together
@@ -97,6 +199,21 @@ impl WriteStream {
     pub fn seekable(&self) -> bool {
         matches!(self.stream, StreamWriter::WriteAndSeek(_))
     }
+
+    /// Wrap this stream into the stream returned by `f`, preserving whether the stream is seekable
+    pub(crate) fn wrap<W, F>(self, f: F) -> Self
+    where
+        W: WriteAndSeek + 'static,
+        F: FnOnce(Self) -> W,
+    {
+        if self.seekable() {
+            let writer: Box<dyn WriteAndSeek> = Box::new(f(self));
+            Self::from(writer)
+        } else {
+            let writer: Box<dyn Write + Send> = Box::new(f(self));
+            Self::from(writer)
+        }
+    }
 }
 
 impl From<Box<dyn Write + Send>> for WriteStream {
@@ -115,6 +232,8 @@ impl From<Box<dyn WriteAndSeek>> for WriteStream {
     }
 }
 
+impl WriteAndSeek for BufWriter<WriteStream> {}
+
 impl Write for WriteStream {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.stream.write(buf)
@@ -160,6 +279,7 @@ impl Seek for StreamWriter {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod test {
 
     use std::fs::File;
@@ -189,6 +309,41 @@ mod test {
         assert_eq!(s.seekable(), true);
     }
 
+    #[test]
+    fn should_count_bytes_read() {
+        let mut temp = NamedTempFile::new().expect("Could not make tempfile");
+        temp.write_all(b"hello, world!").unwrap();
+        let file: Box<dyn ReadAndSeek> =
+            Box::new(File::open(temp.path()).expect("Could not open tempfile"));
+        let mut s = ReadStream::from(file);
+        assert_eq!(s.bytes_read(), 0);
+        let mut buf = [0; 5];
+        s.read_exact(&mut buf).unwrap();
+        assert_eq!(s.bytes_read(), 5);
+        s.seek(std::io::SeekFrom::Start(0)).unwrap();
+        assert_eq!(s.bytes_read(), 5);
+        let mut buf = vec![];
+        s.read_to_end(&mut buf).unwrap();
+        assert_eq!(s.bytes_read(), 18);
+    }
+
+    #[test]
+    fn should_read_lines() {
+        let reader: Box<dyn Read + Send> = Box::new(std::io::Cursor::new(
+            b"first\r\nsecond\n\n\xff\xfe\nlast".to_vec(),
+        ));
+        let mut lines = LineReader::new(ReadStream::from(reader));
+        assert_eq!(lines.next().unwrap().unwrap(), "first");
+        assert_eq!(lines.next().unwrap().unwrap(), "second");
+        assert_eq!(lines.next().unwrap().unwrap(), "");
+        let err = lines.next().unwrap().unwrap_err();
+        assert_eq!(err.kind, RemoteErrorType::BadFile);
+        assert_eq!(err.msg.as_deref(), Some("line 4 is not valid UTF-8"));
+        assert_eq!(lines.next().unwrap().unwrap(), "last");
+        assert!(lines.next().is_none());
+        assert_eq!(lines.into_inner().bytes_read(), 22);
+    }
+
     #[test]
     fn should_create_new_write_stream_from_write() {
         let temp = NamedTempFile::new().expect("Could not make tempfile");
@@ -1,15 +1,25 @@
+use std::collections::{HashMap, HashSet};
 use std::io;
-use std::io::{Read, Write};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::SystemTime;
 
 #[cfg(feature = "find")]
 use wildmatch::WildMatch;
 
 use super::{
-    File, Metadata, ReadStream, RemoteError, RemoteErrorType, UnixPex, Welcome, WriteStream,
+    compare_trees, rewrite_prefix, DirSummary, EntryFilter, ExecOutput, File, HostKeyVerifier,
+    LineReader, ListOptions, ListOrder, Metadata, PathStatus, RandomAccessFile, ReadStream,
+    RemoteError, RemoteErrorType, SymlinkChain, TreeDiff, UnixPex, WalkOptions, Welcome,
+    WriteStream, DEFAULT_BUFFER_SIZE,
 };
+use crate::utils::digest::Sha256;
 use crate::RemoteResult;
 
+/// Maximum amount of threads, each one with its own session, used by [`RemoteFs::download_parallel`]
+pub const MAX_PARALLEL_DOWNLOADS: usize = 8;
+
 /// Defines the methods which must be implemented in order to setup a Remote file system
 pub trait RemoteFs {
     /// Connect to the remote server and authenticate.
@@ -17,12 +27,60 @@ pub trait RemoteFs {
     /// If client has already established connection, then [`RemoteErrorType::AlreadyConnected`] error is returned.
     fn connect(&mut self) -> RemoteResult<Welcome>;
 
+    /// Set the callback verifying the key presented by the remote host on [`RemoteFs::connect`].
+    /// If the callback returns [`HostKeyDecision::Reject`](crate::fs::HostKeyDecision::Reject), the connection fails with [`RemoteErrorType::HostKeyMismatch`].
+    /// If it returns [`HostKeyDecision::AcceptAndStore`](crate::fs::HostKeyDecision::AcceptAndStore), the key is stored as trusted, where the protocol supports it.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default the verifier is ignored, since most protocols don't authenticate the remote host with a key.
+    /// Protocols authenticating the remote host (such as SSH) must implement this method
+    fn set_host_key_verifier(&mut self, _verifier: HostKeyVerifier) {}
+
     /// Disconnect from the remote server
     fn disconnect(&mut self) -> RemoteResult<()>;
 
     /// Gets whether the client is connected to remote
     fn is_connected(&mut self) -> bool;
 
+    /// Get the last working directory known by the client, without querying the remote server.
+    ///
+    /// This is the working directory recorded on the last successful [`RemoteFs::connect`] or [`RemoteFs::change_dir`],
+    /// and it is used by [`RemoteFs::reconnect`] to restore the working directory once the connection has dropped.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method returns `None`; protocols should record the working directory
+    /// and return it, so that it survives the loss of the connection
+    fn last_working_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Re-establish the connection with the remote server, e.g. after it has been dropped.
+    /// The working directory is restored, so that relative paths keep resolving to the same files.
+    /// If the connection has dropped, [`RemoteFs::pwd`] may fail, in which case the working directory
+    /// returned by [`RemoteFs::last_working_dir`] is restored.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method gets the working directory with [`RemoteFs::pwd`],
+    /// or [`RemoteFs::last_working_dir`] if it fails, disconnects if connected,
+    /// connects with [`RemoteFs::connect`] and then restores the working directory with [`RemoteFs::change_dir`]
+    fn reconnect(&mut self) -> RemoteResult<Welcome> {
+        let wrkdir = self.pwd().ok().or_else(|| self.last_working_dir());
+        if self.is_connected() {
+            if let Err(err) = self.disconnect() {
+                debug!("Failed to disconnect before reconnecting: {}", err);
+            }
+        }
+        let welcome = self.connect()?;
+        if let Some(wrkdir) = wrkdir {
+            debug!("Restoring working directory {}", wrkdir.display());
+            self.change_dir(wrkdir.as_path())?;
+        }
+        Ok(welcome)
+    }
+
     /// Get working directory
     fn pwd(&mut self) -> RemoteResult<PathBuf>;
 
@@ -30,18 +88,276 @@ pub trait RemoteFs {
     /// Returns the realpath of new directory
     fn change_dir(&mut self, dir: &Path) -> RemoteResult<PathBuf>;
 
-    /// List directory entries at specified `path`
+    /// List directory entries at specified `path`, sorted by name.
+    /// Protocols whose server may return the entries in any order should sort them before returning;
+    /// anyway, the provided methods of this trait which list directories don't rely on it,
+    /// since they list them with [`RemoteFs::list_dir_with`], which sorts the entries on the client side.
     fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>>;
 
+    /// List directory entries at specified `path`, according to `options`.
+    /// By default, entries are sorted by name, so the result is the same regardless of the order provided by the server.
+    /// With [`ListOptions::resolve_symlinks`], the target of the symlinks is populated even if the listing doesn't report it.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::list_dir`] and sorts the entries on the client side.
+    /// Missing symlink targets are read with a single call to [`RemoteFs::read_links`];
+    /// if the target of a symlink can't be read, the error is logged and the target is left unset
+    fn list_dir_with(&mut self, path: &Path, options: &ListOptions) -> RemoteResult<Vec<File>> {
+        let mut entries = self.list_dir(path)?;
+        if options.resolve_symlinks {
+            let unresolved: Vec<usize> = entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.is_symlink() && entry.metadata.symlink.is_none())
+                .map(|(i, _)| i)
+                .collect();
+            let paths: Vec<PathBuf> = unresolved
+                .iter()
+                .map(|i| entries[*i].path.clone())
+                .collect();
+            for (i, target) in unresolved.into_iter().zip(self.read_links(&paths)) {
+                match target {
+                    Ok(target) => entries[i].metadata.symlink = Some(target),
+                    Err(err) => debug!(
+                        "Failed to read symlink target of {}: {}",
+                        entries[i].path.display(),
+                        err
+                    ),
+                }
+            }
+        }
+        if options.order == ListOrder::Name {
+            entries.sort_by_key(|entry| entry.name());
+        }
+        Ok(entries)
+    }
+
+    /// List directory entries at `dir`, returning the amount of files and directories and the total size of the files
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::list_dir`] and summarizes the entries with [`DirSummary::from_entries`]
+    fn list_dir_summary(&mut self, dir: &Path) -> RemoteResult<DirSummary> {
+        let entries = self.list_dir(dir)?;
+        Ok(DirSummary::from_entries(&entries))
+    }
+
+    /// List directory entries at `dir` and compare them against a `previous` listing of the same directory,
+    /// returning the entries which have been added, removed or modified since then.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::list_dir`] and compares the listings with [`compare_trees`]
+    fn list_dir_diff(&mut self, dir: &Path, previous: &[File]) -> RemoteResult<TreeDiff> {
+        let current = self.list_dir(dir)?;
+        Ok(compare_trees(previous, &current))
+    }
+
     /// Stat file at specified `path` and return [`File`]
     fn stat(&mut self, path: &Path) -> RemoteResult<File>;
 
+    /// Stat each path in `paths`, returning the results in the same order of `paths`.
+    /// Duplicate paths are stated only once, and their result is copied to each position they appear at.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::stat`] once for each unique path
+    fn stat_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<File>> {
+        coalesce(paths, |path| self.stat(path))
+    }
+
+    /// Read the target of the symlink at `path`, without resolving it.
+    /// Fails with [`RemoteErrorType::BadFile`] if `path` is not a symlink
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method returns [`Metadata::symlink`] as returned by [`RemoteFs::stat`]
+    fn read_link(&mut self, path: &Path) -> RemoteResult<PathBuf> {
+        let file = self.stat(path)?;
+        match file.metadata.symlink {
+            Some(target) if file.is_symlink() => Ok(target),
+            _ => Err(RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                format!("{} is not a symlink", path.display()),
+            )),
+        }
+    }
+
+    /// Read the target of each symlink in `paths`, as [`RemoteFs::read_link`] does,
+    /// returning the results in the same order of `paths`.
+    /// Protocols which can read several links in a single request should override this method.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::read_link`] for each path
+    fn read_links(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<PathBuf>> {
+        paths.iter().map(|path| self.read_link(path)).collect()
+    }
+
+    /// Refresh the metadata of each file in `entries`, stating its path again.
+    /// Returns the result of each refresh, in the same order of `entries`.
+    /// The metadata of a file is updated only on success; files which don't exist anymore
+    /// return a [`RemoteErrorType::NoSuchFileOrDirectory`] error, so they can be removed by the caller.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::stat`] for each file
+    fn refresh_entries(&mut self, entries: &mut [File]) -> Vec<RemoteResult<()>> {
+        entries
+            .iter_mut()
+            .map(|entry| {
+                entry.metadata = self.stat(entry.path())?.metadata;
+                Ok(())
+            })
+            .collect()
+    }
+
+    /// Returns whether `a` and `b` are on the same device, comparing [`Metadata::dev`].
+    /// Returns `None` if the device id of either path is unknown.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::stat`] for both paths
+    fn same_device(&mut self, a: &Path, b: &Path) -> RemoteResult<Option<bool>> {
+        let a = self.stat(a)?.metadata.dev;
+        let b = self.stat(b)?.metadata.dev;
+        Ok(a.zip(b).map(|(a, b)| a == b))
+    }
+
+    /// Resolve the chain of symbolic links starting at `path`.
+    /// Returns each step, from `path` itself to the final target, which is not a symlink (e.g. `[a, b, c]` for `a -> b -> c`).
+    /// Relative targets are resolved against the directory containing the link.
+    ///
+    /// If the chain is broken, the returned [`SymlinkChain`] reports the steps resolved so far,
+    /// ending with the missing target, in [`SymlinkChain::broken_at`].
+    /// If `path` itself doesn't exist, a [`RemoteErrorType::NoSuchFileOrDirectory`] error is returned.
+    /// Chains longer than 40 links (e.g. loops) return a [`RemoteErrorType::BadFile`] error.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method follows the links with [`RemoteFs::stat`], which must not follow symlinks
+    fn resolve_chain(&mut self, path: &Path) -> RemoteResult<SymlinkChain> {
+        const MAX_LINKS: usize = 40;
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let mut chain = vec![crate::utils::path::absolutize(&self.pwd()?, path)];
+        loop {
+            let current = chain.last().unwrap().clone();
+            let entry = match self.stat(current.as_path()) {
+                Ok(entry) => entry,
+                Err(err)
+                    if chain.len() > 1 && err.kind == RemoteErrorType::NoSuchFileOrDirectory =>
+                {
+                    return Ok(SymlinkChain {
+                        steps: chain,
+                        broken_at: Some(current),
+                    });
+                }
+                Err(err) => return Err(err),
+            };
+            let target = match (entry.is_symlink(), entry.metadata.symlink) {
+                (true, Some(target)) => target,
+                _ => {
+                    return Ok(SymlinkChain {
+                        steps: chain,
+                        broken_at: None,
+                    })
+                }
+            };
+            if chain.len() > MAX_LINKS {
+                return Err(RemoteError::new_ex(
+                    RemoteErrorType::BadFile,
+                    format!("too many levels of symbolic links at {}", path.display()),
+                ));
+            }
+            let parent = current.parent().unwrap_or_else(|| Path::new("/"));
+            chain.push(crate::utils::path::absolutize(parent, target.as_path()));
+        }
+    }
+
     /// Set metadata for file at specified `path`
     fn setstat(&mut self, path: &Path, metadata: Metadata) -> RemoteResult<()>;
 
+    /// Set access and modification times for file at specified `path`, as `touch` does.
+    ///
+    /// A `None` time is set to **now**, so `set_times(path, None, None)` sets both times to the current time.
+    /// Since the server time is not known to the client, "now" is the local system time.
+    /// To leave a time unchanged, use [`RemoteFs::update_times`] instead.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method combines [`RemoteFs::stat`] and [`RemoteFs::setstat`]
+    fn set_times(
+        &mut self,
+        path: &Path,
+        accessed: Option<SystemTime>,
+        modified: Option<SystemTime>,
+    ) -> RemoteResult<()> {
+        let now = SystemTime::now();
+        self.update_times(path, accessed.or(Some(now)), modified.or(Some(now)))
+    }
+
+    /// Update access and modification times for file at specified `path`.
+    ///
+    /// Unlike [`RemoteFs::set_times`], a `None` time is left **unchanged**.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method combines [`RemoteFs::stat`] and [`RemoteFs::setstat`]
+    fn update_times(
+        &mut self,
+        path: &Path,
+        accessed: Option<SystemTime>,
+        modified: Option<SystemTime>,
+    ) -> RemoteResult<()> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let mut metadata = self.stat(path)?.metadata;
+        if let Some(accessed) = accessed {
+            metadata.accessed = Some(accessed);
+        }
+        if let Some(modified) = modified {
+            metadata.modified = Some(modified);
+        }
+        self.setstat(path, metadata)
+    }
+
     /// Returns whether file at specified `path` exists.
     fn exists(&mut self, path: &Path) -> RemoteResult<bool>;
 
+    /// Returns whether each path in `paths` exists, in the same order of `paths`.
+    /// Duplicate paths are checked only once, and their result is copied to each position they appear at.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::exists`] once for each unique path
+    fn exists_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<bool>> {
+        coalesce(paths, |path| self.exists(path))
+    }
+
+    /// Probe `path`, returning whether it exists and its kind in a single call.
+    /// Symbolic links are not followed.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::stat`], mapping a [`RemoteErrorType::NoSuchFileOrDirectory`] error
+    /// to [`PathStatus::Missing`]
+    fn probe_path(&mut self, path: &Path) -> RemoteResult<PathStatus> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        match self.stat(path) {
+            Ok(file) => Ok(PathStatus::from(file)),
+            Err(err) if err.kind == RemoteErrorType::NoSuchFileOrDirectory => {
+                Ok(PathStatus::Missing)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
     /// Remove file at specified `path`.
     /// Fails if is not a file or doesn't exist
     fn remove_file(&mut self, path: &Path) -> RemoteResult<()>;
@@ -71,7 +387,8 @@ pub trait RemoteFs {
                     "{} is a directory; removing all directory entries",
                     entry.name()
                 );
-                let directory_content = self.list_dir(entry.path())?;
+                let directory_content =
+                    self.list_dir_with(entry.path(), &ListOptions::default())?;
                 for entry in directory_content.iter() {
                     self.remove_dir_all(entry.path())?;
                 }
@@ -88,11 +405,140 @@ pub trait RemoteFs {
         }
     }
 
+    /// Plan the removal of `path` and all its contents, as [`RemoteFs::remove_dir_all`] would do,
+    /// without removing anything.
+    /// Returns the absolute paths to remove, in deletion order: the content of each directory precedes the directory,
+    /// and `path` itself comes last.
+    /// The plan can be shown to the user before being executed with [`RemoteFs::execute_removal_plan`]
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method walks `path` with [`RemoteFs::walk`], reversing the walked entries
+    fn plan_remove_dir_all(&mut self, path: &Path) -> RemoteResult<Vec<PathBuf>> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let path = crate::utils::path::absolutize(&self.pwd()?, path);
+        let entry = self.stat(path.as_path())?;
+        let mut plan = match entry.is_dir() {
+            true => self.walk(entry.path(), &WalkOptions::default())?,
+            false => Vec::new(),
+        };
+        plan.reverse();
+        plan.push(entry);
+        debug!("planned removal of {} paths", plan.len());
+        Ok(plan.into_iter().map(|entry| entry.path).collect())
+    }
+
+    /// Remove the paths in `plan`, in order, as returned by [`RemoteFs::plan_remove_dir_all`].
+    /// Directories are removed with [`RemoteFs::remove_dir`], so they must be empty by the time they're removed.
+    /// Execution stops at the first error, leaving the remaining paths in place.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method stats each path, to remove it with either [`RemoteFs::remove_dir`] or [`RemoteFs::remove_file`]
+    fn execute_removal_plan(&mut self, plan: &[PathBuf]) -> RemoteResult<()> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        for path in plan {
+            trace!("removing {}", path.display());
+            match self.stat(path)?.is_dir() {
+                true => self.remove_dir(path)?,
+                false => self.remove_file(path)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the file at `path` into `trash_dir` instead of removing it, so that it can be recovered.
+    /// `trash_dir` is created if it doesn't exist. If a file with the same name already exists in `trash_dir`,
+    /// the file is renamed with a numeric suffix, such as `a (1).txt`.
+    /// Returns the path of the file in the trash directory.
+    /// Fails if `path` is a directory
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method moves the file with [`RemoteFs::move_with_fallback`],
+    /// so the file is copied and then removed if it can't be moved (e.g. because `trash_dir` is on another device)
+    fn remove_file_to_trash(&mut self, path: &Path, trash_dir: &Path) -> RemoteResult<PathBuf> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let wrkdir = self.pwd()?;
+        let path = crate::utils::path::absolutize(&wrkdir, path);
+        let trash_dir = crate::utils::path::absolutize(&wrkdir, trash_dir);
+        let entry = self.stat(path.as_path())?;
+        if entry.is_dir() {
+            return Err(RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                format!("{} is a directory", path.display()),
+            ));
+        }
+        if !self.exists(trash_dir.as_path())? {
+            self.create_dir(trash_dir.as_path(), UnixPex::from(0o700))?;
+        }
+        // find a free name in trash
+        let stem = entry
+            .path()
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut dest = trash_dir.join(entry.name());
+        let mut n = 1;
+        while self.exists(dest.as_path())? {
+            let name = match entry.extension() {
+                Some(ext) => format!("{stem} ({n}).{ext}"),
+                None => format!("{stem} ({n})"),
+            };
+            dest = trash_dir.join(name);
+            n += 1;
+        }
+        debug!("Moving {} to trash {}", path.display(), dest.display());
+        self.move_with_fallback(path.as_path(), dest.as_path())?;
+        Ok(dest)
+    }
+
     /// Create a directory at `path` with specified mode.
     ///
     /// If the directory already exists, it **MUST** return [`RemoteErrorType::DirectoryAlreadyExists`]
     fn create_dir(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()>;
 
+    /// Create a directory at `path` as [`RemoteFs::create_dir`] does, making sure it gets exactly `mode`.
+    /// The server may strip bits from the mode of new directories (e.g. with its umask, `0o770` may become `0o750`),
+    /// so the mode is applied again once the directory has been created.
+    /// This costs up to two more round trips: a stat, and a setstat if the mode differs.
+    /// If the mode can't be applied, the directory is removed, so that the call can be retried.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::create_dir`], then [`RemoteFs::stat`]
+    /// and [`RemoteFs::setstat`], with only the mode set, if the mode of the directory isn't `mode`
+    fn create_dir_exact(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()> {
+        self.create_dir(path, mode)?;
+        let metadata = self.stat(path)?.metadata;
+        if metadata.mode == Some(mode) {
+            return Ok(());
+        }
+        debug!(
+            "{} has been created with mode {:?}; applying {:o}",
+            path.display(),
+            metadata.mode.map(u32::from),
+            u32::from(mode)
+        );
+        if let Err(err) = self.setstat(path, Metadata::default().mode(mode)) {
+            debug!(
+                "Removing {}, since its mode can't be applied",
+                path.display()
+            );
+            if let Err(err) = self.remove_dir(path) {
+                debug!("Failed to remove {}: {}", path.display(), err);
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
     /// Create a symlink at `path` pointing at `target`
     fn symlink(&mut self, path: &Path, target: &Path) -> RemoteResult<()>;
 
@@ -102,10 +548,148 @@ pub trait RemoteFs {
     /// move file/directory from `src` to `dest`
     fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()>;
 
+    /// Move file/directory from `src` to `dest`, falling back to a copy followed by a removal
+    /// if [`RemoteFs::mov`] fails with [`RemoteErrorType::UnsupportedFeature`] or [`RemoteErrorType::CrossDevice`]
+    /// (e.g. because `src` and `dest` are on different devices). Any other error is returned as is.
+    ///
+    /// The fallback is indistinguishable from a rename, metadata-wise: once copied, the metadata of `src`
+    /// (and of each entry in it, if a directory) is applied to the copy with [`RemoteFs::setstat`],
+    /// so modify times, permissions and ownership are preserved. `src` is removed only once the metadata has been applied.
+    /// If applying the metadata or removing `src` fails, the copy is removed, unless `src` has been partially removed.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method falls back to [`RemoteFs::copy`], [`RemoteFs::setstat`] and [`RemoteFs::remove_dir_all`]
+    fn move_with_fallback(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let wrkdir = self.pwd()?;
+        let src = crate::utils::path::absolutize(&wrkdir, src);
+        let dest = crate::utils::path::absolutize(&wrkdir, dest);
+        let err = match self.mov(src.as_path(), dest.as_path()) {
+            Ok(()) => return Ok(()),
+            Err(err)
+                if matches!(
+                    err.kind,
+                    RemoteErrorType::UnsupportedFeature | RemoteErrorType::CrossDevice
+                ) =>
+            {
+                err
+            }
+            Err(err) => return Err(err),
+        };
+        debug!(
+            "Could not move {} to {} ({}); copying it",
+            src.display(),
+            dest.display(),
+            err
+        );
+        move_by_copy(self, src.as_path(), dest.as_path())
+    }
+
+    /// Copy the directory `src` to `dest` recursively, resuming a copy which has been interrupted.
+    /// Files already present at the destination with the same size and modify time of the source are skipped,
+    /// so only the missing or changed files are copied; missing directories are created.
+    /// Each copied file gets the metadata of its source, so that it can be skipped by a later resume.
+    /// If the server doesn't support [`RemoteFs::setstat`], the modify time of the copies can't be preserved,
+    /// so once it has been found unsupported, files are compared by size only.
+    /// Returns the amount of files copied
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method walks `src` with [`RemoteFs::walk`], copying each missing or changed file with [`RemoteFs::copy`]
+    /// and applying its metadata with [`RemoteFs::setstat`]
+    fn copy_dir_resume(&mut self, src: &Path, dest: &Path) -> RemoteResult<u64> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let wrkdir = self.pwd()?;
+        let src = crate::utils::path::absolutize(&wrkdir, src);
+        let dest = crate::utils::path::absolutize(&wrkdir, dest);
+        let root = self.stat(src.as_path())?;
+        if !root.is_dir() {
+            return Err(RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                format!("{} is not a directory", src.display()),
+            ));
+        }
+        let mut entries = self.walk(src.as_path(), &WalkOptions::default())?;
+        entries.insert(0, root);
+        let mut targets = entries.clone();
+        rewrite_prefix(&mut targets, src.as_path(), dest.as_path());
+        let mut copied = 0;
+        let mut size_only = false;
+        for (entry, target) in entries.into_iter().zip(targets) {
+            let existing = match self.stat(target.path()) {
+                Ok(existing) => Some(existing),
+                Err(err) if err.kind == RemoteErrorType::NoSuchFileOrDirectory => None,
+                Err(err) => return Err(err),
+            };
+            if entry.is_dir() {
+                if existing.is_none() {
+                    let mode = entry.metadata.mode.unwrap_or(UnixPex::DIR);
+                    self.create_dir(target.path(), mode)?;
+                }
+                continue;
+            }
+            match existing {
+                Some(existing)
+                    if existing.metadata.file_type == entry.metadata.file_type
+                        && existing.metadata.size == entry.metadata.size
+                        && (size_only || existing.metadata.modified == entry.metadata.modified) =>
+                {
+                    trace!("{} is already copied", target.path().display());
+                    continue;
+                }
+                Some(existing) if existing.is_dir() => self.remove_dir_all(target.path())?,
+                Some(_) => self.remove_file(target.path())?,
+                None => {}
+            }
+            debug!(
+                "copying {} to {}",
+                entry.path().display(),
+                target.path().display()
+            );
+            self.copy(entry.path(), target.path())?;
+            match self.setstat(target.path(), entry.metadata) {
+                Ok(()) => {}
+                Err(err) if err.kind == RemoteErrorType::UnsupportedFeature => {
+                    debug!(
+                        "Could not apply metadata to {} ({}); comparing files by size only",
+                        target.path().display(),
+                        err
+                    );
+                    size_only = true;
+                }
+                Err(err) => return Err(err),
+            }
+            copied += 1;
+        }
+        Ok(copied)
+    }
+
     /// Execute a command on remote host if supported by host.
     /// Returns command exit code and output (stdout)
     fn exec(&mut self, cmd: &str) -> RemoteResult<(u32, String)>;
 
+    /// Execute a command on remote host as [`RemoteFs::exec`] does, streaming the content of `stdin` to its stdin,
+    /// which is closed once `stdin` is exhausted. Useful to run commands such as `cat > file`.
+    ///
+    /// If the command closes its stdin before reading the whole input, the rest of the input is not sent
+    /// and the command output is still returned, with [`ExecOutput::stdin_written`] reporting the bytes sent.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method returns [`RemoteErrorType::UnsupportedFeature`]
+    fn exec_with_stdin(
+        &mut self,
+        _cmd: &str,
+        _stdin: Box<dyn Read + Send>,
+    ) -> RemoteResult<ExecOutput> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
     /// Open file at `path` for appending data.
     /// If the file doesn't exist, the file is created.
     ///
@@ -127,6 +711,186 @@ pub trait RemoteFs {
     /// Open file at specified path for read.
     fn open(&mut self, path: &Path) -> RemoteResult<ReadStream>;
 
+    /// Open file at specified path for read, starting at `offset`.
+    /// If `len` is provided, at most `len` bytes are read from the stream.
+    /// The returned stream is not seekable and must be finalized with [`RemoteFs::on_read`].
+    ///
+    /// Along with [`ReadStream::bytes_read`], this method can be used to resume a broken transfer.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function opens the file with [`RemoteFs::open`] and then seeks to `offset`.
+    /// If the stream is not seekable, the first `offset` bytes are read and discarded.
+    /// Implement this method if the protocol supports ranged reads
+    fn open_range(
+        &mut self,
+        path: &Path,
+        offset: u64,
+        len: Option<u64>,
+    ) -> RemoteResult<ReadStream> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let mut stream = self.open(path)?;
+        if stream.seekable() {
+            stream
+                .seek(SeekFrom::Start(offset))
+                .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+        } else {
+            io::copy(&mut (&mut stream).take(offset), &mut io::sink())
+                .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+        }
+        trace!("Opened {} at offset {}", path.display(), offset);
+        let reader: Box<dyn Read + Send> = Box::new(stream.take(len.unwrap_or(u64::MAX)));
+        Ok(ReadStream::from(reader))
+    }
+
+    /// Open the file at `path` for random access, returning a handle to read it at arbitrary offsets,
+    /// as `pread` does. Recently read blocks are cached by the handle; see [`RandomAccessFile`].
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method stats the file, which is then read in blocks with [`RemoteFs::open_range`]
+    fn open_random(&mut self, path: &Path) -> RemoteResult<RandomAccessFile<'_, Self>>
+    where
+        Self: Sized,
+    {
+        RandomAccessFile::new(self, path)
+    }
+
+    /// Open the text file at `path`, returning an iterator over its lines.
+    /// See [`LineReader`] for how lines are split and decoded.
+    /// Once done, the stream must be finalized with [`RemoteFs::on_read`], getting it with [`LineReader::into_inner`].
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method opens the file with [`RemoteFs::open`]
+    fn read_lines(&mut self, path: &Path) -> RemoteResult<LineReader> {
+        self.open(path).map(LineReader::new)
+    }
+
+    /// Read multiple byte `ranges` of the file at `path`, given as `(offset, len)` pairs.
+    /// Returns a buffer for each range, in the same order of `ranges`.
+    /// A range exceeding the end of the file returns only the bytes up to the end of the file.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function opens the file once with [`RemoteFs::open`] and seeks to each range.
+    /// If the stream is not seekable, each range is read with [`RemoteFs::open_range`].
+    /// Implement this method if the protocol can fetch multiple ranges with a single request
+    fn read_vectored(&mut self, path: &Path, ranges: &[(u64, u64)]) -> RemoteResult<Vec<Vec<u8>>> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let read_range = |stream: &mut ReadStream, len: u64| -> RemoteResult<Vec<u8>> {
+            let mut buf = Vec::new();
+            stream
+                .take(len)
+                .read_to_end(&mut buf)
+                .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+            Ok(buf)
+        };
+        let mut stream = self.open(path)?;
+        if stream.seekable() {
+            let mut segments = Vec::with_capacity(ranges.len());
+            for (offset, len) in ranges {
+                stream.seek(SeekFrom::Start(*offset)).map_err(|e| {
+                    RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string())
+                })?;
+                segments.push(read_range(&mut stream, *len)?);
+            }
+            self.on_read(stream)?;
+            return Ok(segments);
+        }
+        self.on_read(stream)?;
+        ranges
+            .iter()
+            .map(|(offset, len)| {
+                let mut stream = self.open_range(path, *offset, Some(*len))?;
+                let segment = read_range(&mut stream, *len)?;
+                self.on_read(stream)?;
+                Ok(segment)
+            })
+            .collect()
+    }
+
+    /// Open the alternate data stream (or resource fork) named `stream` of the file at `path` for read.
+    /// The names of the streams of a file are listed in [`Metadata::streams`].
+    /// The returned stream must be finalized with [`RemoteFs::on_read`].
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function returns [`RemoteErrorType::UnsupportedFeature`]
+    fn open_named_stream(&mut self, _path: &Path, _stream: &str) -> RemoteResult<ReadStream> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    /// Create the alternate data stream (or resource fork) named `stream` of the file at `path` for write.
+    /// If the stream already exists, its content will be overwritten.
+    /// The returned stream must be finalized with [`RemoteFs::on_written`].
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function returns [`RemoteErrorType::UnsupportedFeature`]
+    fn create_named_stream(&mut self, _path: &Path, _stream: &str) -> RemoteResult<WriteStream> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    /// Get the size of the buffer used to transfer data with the remote server.
+    ///
+    /// This is the chunk size used by [`RemoteFs::append_file`], [`RemoteFs::create_file`], [`RemoteFs::open_file`]
+    /// and [`RemoteFs::download_verified`],
+    /// and the default buffer size of [`RemoteFs::open_buffered`] and [`RemoteFs::create_buffered`].
+    /// Larger buffers increase throughput on high-bandwidth links, at the cost of memory;
+    /// smaller buffers are preferable in low-memory environments.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method returns [`DEFAULT_BUFFER_SIZE`]; protocols should return the optimal chunk size
+    /// for the protocol, such as the maximum packet size
+    fn buffer_size(&self) -> usize {
+        DEFAULT_BUFFER_SIZE
+    }
+
+    /// Open a file for reading as [`RemoteFs::open`] does, buffering reads with a buffer of `buffer_size` bytes,
+    /// or of [`RemoteFs::buffer_size`] bytes if `None`.
+    /// Reads from the returned stream are served from the buffer, which is refilled reading `buffer_size` bytes
+    /// at a time from the remote file.
+    /// The stream must be finalized with [`RemoteFs::on_read`]
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method wraps the stream returned by [`RemoteFs::open`] into a [`BufReader`]
+    fn open_buffered(
+        &mut self,
+        path: &Path,
+        buffer_size: Option<usize>,
+    ) -> RemoteResult<ReadStream> {
+        let capacity = buffer_size.unwrap_or_else(|| self.buffer_size());
+        let stream = self.open(path)?;
+        Ok(stream.wrap(|stream| BufReader::with_capacity(capacity, stream)))
+    }
+
+    /// Create a file for writing as [`RemoteFs::create`] does, buffering writes with a buffer of `buffer_size` bytes,
+    /// or of [`RemoteFs::buffer_size`] bytes if `None`.
+    /// Writes to the returned stream are collected into the buffer, which is written to the remote file once full.
+    /// The stream must be flushed before being finalized with [`RemoteFs::on_written`],
+    /// otherwise errors writing the last chunk are lost
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method wraps the stream returned by [`RemoteFs::create`] into a [`BufWriter`]
+    fn create_buffered(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        buffer_size: Option<usize>,
+    ) -> RemoteResult<WriteStream> {
+        let capacity = buffer_size.unwrap_or_else(|| self.buffer_size());
+        let stream = self.create(path, metadata)?;
+        Ok(stream.wrap(|stream| BufWriter::with_capacity(capacity, stream)))
+    }
+
     /// Finalize [`RemoteFs::create`] and [`RemoteFs::append`] methods.
     /// This method must be implemented only if necessary; in case you don't need it, just return [`Ok`]
     /// The purpose of this method is to finalize the connection with the peer when writing data.
@@ -153,6 +917,18 @@ pub trait RemoteFs {
         Ok(())
     }
 
+    /// Request the server to flush the file at `path` to stable storage,
+    /// so that its content is durable once this method returns (e.g. `fsync@openssh.com` on SFTP).
+    /// Call this method after the write has been finalized with [`RemoteFs::on_written`].
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function returns [`RemoteErrorType::UnsupportedFeature`],
+    /// since most protocols don't provide a way to flush a file on the server
+    fn fsync(&mut self, _path: &Path) -> RemoteResult<()> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
     /// Blocking implementation of [`RemoteFs::append`]
     /// This method **SHOULD** be implemented **ONLY** when streams are not supported by the current file transfer.
     /// The developer using the client should FIRST try with `create` followed by `on_written`
@@ -171,7 +947,7 @@ pub trait RemoteFs {
         if self.is_connected() {
             trace!("Opened remote file");
             let mut stream = self.append(path, metadata)?;
-            let sz = io::copy(&mut reader, &mut stream)
+            let sz = copy_buffered(&mut reader, &mut stream, self.buffer_size())
                 .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
             self.on_written(stream)?;
             trace!("Written {} bytes to destination", sz);
@@ -199,7 +975,7 @@ pub trait RemoteFs {
         if self.is_connected() {
             let mut stream = self.create(path, metadata)?;
             trace!("Opened remote file");
-            let sz = io::copy(&mut reader, &mut stream)
+            let sz = copy_buffered(&mut reader, &mut stream, self.buffer_size())
                 .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
             self.on_written(stream)?;
             trace!("Written {} bytes to destination", sz);
@@ -209,6 +985,38 @@ pub trait RemoteFs {
         }
     }
 
+    /// Upload the content of `reader` to `path` as [`RemoteFs::create_file`] does, verifying that exactly `expected_len` bytes
+    /// have been uploaded. Both the amount of bytes written and the size of the remote file, once uploaded, are checked.
+    /// In case of mismatch, [`RemoteErrorType::IncompleteTransfer`] is returned and the remote file is left as is.
+    /// In case of success, returns the amount of bytes written to the remote file
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function calls [`RemoteFs::create_file`] and then [`RemoteFs::stat`]
+    fn create_file_checked(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+        expected_len: u64,
+    ) -> RemoteResult<u64> {
+        let written = self.create_file(path, metadata, reader)?;
+        if written != expected_len {
+            return Err(RemoteError::new_ex(
+                RemoteErrorType::IncompleteTransfer,
+                format!("expected {expected_len} bytes, but {written} bytes were written"),
+            ));
+        }
+        let size = self.stat(path)?.metadata.size;
+        if size != expected_len {
+            return Err(RemoteError::new_ex(
+                RemoteErrorType::IncompleteTransfer,
+                format!("expected {expected_len} bytes, but the remote file has {size} bytes"),
+            ));
+        }
+        Ok(written)
+    }
+
     /// Blocking implementation of [`RemoteFs::open`]
     /// This method SHOULD be implemented ONLY when streams are not supported by the current file transfer.
     /// (since it would work thanks to the default implementation)
@@ -224,7 +1032,7 @@ pub trait RemoteFs {
             let mut dest = vec![];
             let mut stream = self.open(src)?;
             trace!("File opened");
-            let sz = io::copy(&mut stream, &mut dest)
+            let sz = copy_buffered(&mut stream, &mut dest, self.buffer_size())
                 .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
             self.on_read(stream)?;
             trace!("Copied {} bytes to destination", sz);
@@ -234,9 +1042,360 @@ pub trait RemoteFs {
         }
     }
 
-    /// Find files from current directory (in all subdirectories) whose name matches the provided search
-    /// Search supports wildcards ('?', '*')
-    #[cfg(feature = "find")]
+    /// Get the checksum of the file at `path`, computed by the remote server.
+    /// The checksum is the lowercase hex-encoded SHA-256 digest of the file content.
+    /// This method should be implemented only if the protocol provides a way to compute the checksum on the server.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function returns [`RemoteErrorType::UnsupportedFeature`]
+    fn checksum(&mut self, _path: &Path) -> RemoteResult<String> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    /// Download file at `remote` to the `local` path, verifying its content in a single pass.
+    /// Data is written to a temporary file next to `local`, named as `local` with a `.part` suffix,
+    /// while the SHA-256 digest of the file is computed.
+    /// The digest is then compared against `expected` or, if `None`, against the one returned by [`RemoteFs::checksum`].
+    /// If no digest is expected and the server doesn't support checksums, the file is not verified.
+    /// Only once verified, the temporary file is renamed to `local`, replacing it if it exists.
+    /// In case of mismatch, [`RemoteErrorType::ChecksumMismatch`] is returned; on any error, the temporary file is removed
+    /// and `local` is left untouched.
+    /// In case of success, returns the amount of bytes written to the local file
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this function uses [`RemoteFs::open`] and [`RemoteFs::on_read`] to read the remote file
+    fn download_verified(
+        &mut self,
+        remote: &Path,
+        local: &Path,
+        expected: Option<String>,
+    ) -> RemoteResult<u64> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let part = partial_path(local);
+        let mut writer = std::fs::File::create(part.as_path()).map_err(RemoteError::io)?;
+        let download = || -> RemoteResult<u64> {
+            let mut stream = self.open(remote)?;
+            trace!("File opened");
+            let mut hasher = Sha256::default();
+            let mut buffer = vec![0; self.buffer_size()];
+            let mut sz = 0;
+            loop {
+                let read = stream.read(&mut buffer).map_err(|e| {
+                    RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string())
+                })?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                writer.write_all(&buffer[..read]).map_err(RemoteError::io)?;
+                sz += read as u64;
+            }
+            self.on_read(stream)?;
+            trace!("Copied {} bytes to {}", sz, part.display());
+            let digest = hasher.finalize();
+            let expected = match expected {
+                Some(expected) => Some(expected),
+                None => match self.checksum(remote) {
+                    Ok(checksum) => Some(checksum),
+                    Err(err) if err.kind == RemoteErrorType::UnsupportedFeature => None,
+                    Err(err) => return Err(err),
+                },
+            };
+            match expected {
+                Some(expected) if !expected.eq_ignore_ascii_case(&digest) => {
+                    error!(
+                        "checksum mismatch for {}: expected {}, got {}",
+                        remote.display(),
+                        expected,
+                        digest
+                    );
+                    Err(RemoteError::new_ex(
+                        RemoteErrorType::ChecksumMismatch,
+                        format!("expected {}, got {}", expected, digest),
+                    ))
+                }
+                _ => Ok(sz),
+            }
+        };
+        let result = download();
+        drop(writer);
+        complete_partial(part.as_path(), local, result)
+    }
+
+    /// Download file at `remote` to the `local` path, splitting it into `parts` ranges downloaded concurrently.
+    /// Ranges are downloaded by at most [`MAX_PARALLEL_DOWNLOADS`] threads, each one with its own clone of the client,
+    /// which read the ranges with [`RemoteFs::open_range`] and write them at their offset.
+    /// Data is written to a temporary file next to `local`, named as `local` with a `.part` suffix.
+    /// Once all ranges have been downloaded, the size of the temporary file is compared to the size of the remote file,
+    /// returning [`RemoteErrorType::IncompleteTransfer`] if they differ; otherwise it is renamed to `local`.
+    /// On any error, the temporary file is removed and `local` is left untouched.
+    /// `parts` must be greater than 0.
+    /// In case of success, returns the amount of bytes written to the local file
+    ///
+    /// ### ⚠️ Warning
+    ///
+    /// Each clone of the client must be usable as an independent session (e.g. taken from a connection pool),
+    /// and the protocol should support ranged reads, otherwise each part will read the file from the beginning.
+    fn download_parallel(&mut self, remote: &Path, local: &Path, parts: usize) -> RemoteResult<u64>
+    where
+        Self: Sized + Clone + Send,
+    {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        if parts == 0 {
+            return Err(RemoteError::io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the amount of parts must be greater than 0",
+            )));
+        }
+        let size = self.stat(remote)?.metadata.size;
+        let part = partial_path(local);
+        let file = std::fs::File::create(part.as_path()).map_err(RemoteError::io)?;
+        let download = || -> RemoteResult<u64> {
+            file.set_len(size).map_err(RemoteError::io)?;
+            let part_size = size.div_ceil(parts as u64).max(1);
+            let ranges = size.div_ceil(part_size);
+            let workers = (ranges as usize).min(MAX_PARALLEL_DOWNLOADS);
+            debug!(
+                "Downloading {} ({} bytes) in {} parts of {} bytes with {} workers",
+                remote.display(),
+                size,
+                ranges,
+                part_size,
+                workers
+            );
+            // workers pull the index of the next range to download, until all ranges have been taken or one fails
+            let next = AtomicU64::new(0);
+            let failed = AtomicBool::new(false);
+            let results: Vec<RemoteResult<u64>> = std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..workers)
+                    .map(|_| {
+                        let mut client = self.clone();
+                        let (next, failed, part) = (&next, &failed, part.as_path());
+                        scope.spawn(move || -> RemoteResult<u64> {
+                            let mut writer = std::fs::OpenOptions::new()
+                                .write(true)
+                                .open(part)
+                                .map_err(RemoteError::io)?;
+                            let mut written = 0;
+                            loop {
+                                let index = next.fetch_add(1, Ordering::Relaxed);
+                                if index >= ranges || failed.load(Ordering::Relaxed) {
+                                    return Ok(written);
+                                }
+                                let offset = index * part_size;
+                                let len = part_size.min(size - offset);
+                                let result =
+                                    download_range(&mut client, remote, &mut writer, offset, len);
+                                match result {
+                                    Ok(sz) => written += sz,
+                                    Err(err) => {
+                                        failed.store(true, Ordering::Relaxed);
+                                        return Err(err);
+                                    }
+                                }
+                            }
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle.join().unwrap_or_else(|_| {
+                            Err(RemoteError::new_ex(
+                                RemoteErrorType::IoError,
+                                "download thread panicked",
+                            ))
+                        })
+                    })
+                    .collect()
+            });
+            let mut written = 0;
+            for result in results {
+                written += result?;
+            }
+            let local_size = file.metadata().map_err(RemoteError::io)?.len();
+            if written != size || local_size != size {
+                return Err(RemoteError::new_ex(
+                    RemoteErrorType::IncompleteTransfer,
+                    format!("expected {} bytes, downloaded {}", size, written),
+                ));
+            }
+            Ok(written)
+        };
+        let result = download();
+        drop(file);
+        complete_partial(part.as_path(), local, result)
+    }
+
+    /// Walk `dir` recursively, returning all the entries in it, according to `options`.
+    /// Entries are returned in pre-order (each directory precedes its content), sorted by name.
+    /// Symbolic links are not followed.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method traverses the tree with [`RemoteFs::list_dir_with`]
+    fn walk(&mut self, dir: &Path, options: &WalkOptions) -> RemoteResult<Vec<File>> {
+        if !self.is_connected() {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        let mut entries = Vec::new();
+        let mut stack: Vec<(File, usize)> = Vec::new();
+        if options.descends(0) {
+            for entry in self
+                .list_dir_with(dir, &ListOptions::default())?
+                .into_iter()
+                .rev()
+            {
+                stack.push((entry, 1));
+            }
+        }
+        while let Some((entry, depth)) = stack.pop() {
+            if entry.is_dir() && options.descends(depth) {
+                for child in self
+                    .list_dir_with(entry.path(), &ListOptions::default())?
+                    .into_iter()
+                    .rev()
+                {
+                    stack.push((child, depth + 1));
+                }
+            }
+            if options.includes(depth) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Returns whether the directory at `path` has no entries.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method uses [`Metadata::child_count`] if the server reports it, otherwise it lists the directory
+    fn is_dir_empty(&mut self, path: &Path) -> RemoteResult<bool> {
+        let entry = self.stat(path)?;
+        if !entry.is_dir() {
+            return Err(RemoteError::new_ex(
+                RemoteErrorType::BadFile,
+                format!("{} is not a directory", path.display()),
+            ));
+        }
+        match entry.child_count() {
+            Some(count) => Ok(count == 0),
+            None => Ok(self.list_dir(path)?.is_empty()),
+        }
+    }
+
+    /// Walk `dir` recursively, returning the entries matching `filter`, in the same order of [`RemoteFs::walk`].
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method walks `dir` with [`RemoteFs::walk`].
+    /// When the filter can't be told from the metadata of an entry (see [`EntryFilter::matches`]),
+    /// it is evaluated from the walked entries, such as whether a directory is empty;
+    /// entries whose ownership is unknown never match [`EntryFilter::OwnedBy`] and [`EntryFilter::GroupIs`]
+    fn find_filtered(&mut self, dir: &Path, filter: &EntryFilter) -> RemoteResult<Vec<File>> {
+        let entries = self.walk(dir, &WalkOptions::default())?;
+        let parents: HashSet<&Path> = entries
+            .iter()
+            .filter_map(|entry| entry.path().parent())
+            .collect();
+        let matching: Vec<bool> = entries
+            .iter()
+            .map(|entry| match (filter, filter.matches(entry)) {
+                (_, Some(matches)) => matches,
+                (EntryFilter::IsEmpty, None) => !parents.contains(entry.path()),
+                (EntryFilter::OwnedBy(_) | EntryFilter::GroupIs(_), None) => false,
+            })
+            .collect();
+        Ok(entries
+            .into_iter()
+            .zip(matching)
+            .filter_map(|(entry, matches)| matches.then_some(entry))
+            .collect())
+    }
+
+    /// Walk `dir` recursively, returning the regular files with size `0`
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::find_filtered`] with [`EntryFilter::IsEmpty`]
+    fn find_empty_files(&mut self, dir: &Path) -> RemoteResult<Vec<File>> {
+        let mut entries = self.find_filtered(dir, &EntryFilter::IsEmpty)?;
+        entries.retain(|entry| entry.is_file());
+        Ok(entries)
+    }
+
+    /// Walk `dir` recursively, returning the directories with no entries
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method calls [`RemoteFs::find_filtered`] with [`EntryFilter::IsEmpty`]
+    fn find_empty_dirs(&mut self, dir: &Path) -> RemoteResult<Vec<File>> {
+        let mut entries = self.find_filtered(dir, &EntryFilter::IsEmpty)?;
+        entries.retain(|entry| entry.is_dir());
+        Ok(entries)
+    }
+
+    /// Walk `dir` recursively, returning the entries owned by the user with `uid`.
+    /// Entries whose owner is unknown are returned only if `include_unknown` is `true`
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method walks `dir` with [`RemoteFs::walk`], matching entries with [`EntryFilter::OwnedBy`]
+    fn find_owned_by(
+        &mut self,
+        dir: &Path,
+        uid: u32,
+        include_unknown: bool,
+    ) -> RemoteResult<Vec<File>> {
+        let filter = EntryFilter::OwnedBy(uid);
+        let mut entries = self.walk(dir, &WalkOptions::default())?;
+        entries.retain(|entry| filter.matches(entry).unwrap_or(include_unknown));
+        Ok(entries)
+    }
+
+    /// Walk `dir` recursively, returning the symbolic links whose target can't be resolved,
+    /// because the target (or a link along the chain) doesn't exist, or because the chain is a loop.
+    ///
+    /// ### Default implementation
+    ///
+    /// By default this method walks `dir` with [`RemoteFs::walk`], resolving each symlink with [`RemoteFs::resolve_chain`]
+    fn find_broken_symlinks(&mut self, dir: &Path) -> RemoteResult<Vec<File>> {
+        let mut broken = Vec::new();
+        for entry in self.walk(dir, &WalkOptions::default())? {
+            if !entry.is_symlink() {
+                continue;
+            }
+            match self.resolve_chain(entry.path()) {
+                Ok(chain) if chain.is_broken() => {
+                    debug!("{} is a broken symlink: {}", entry.path().display(), chain);
+                    broken.push(entry);
+                }
+                Ok(_) => {}
+                Err(err)
+                    if matches!(
+                        err.kind,
+                        RemoteErrorType::NoSuchFileOrDirectory | RemoteErrorType::BadFile
+                    ) =>
+                {
+                    debug!("{} is a broken symlink: {}", entry.path().display(), err);
+                    broken.push(entry);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(broken)
+    }
+
+    /// Find files from current directory (in all subdirectories) whose name matches the provided search
+    /// Search supports wildcards ('?', '*')
+    #[cfg(feature = "find")]
     fn find(&mut self, search: &str) -> RemoteResult<Vec<File>> {
         match self.is_connected() {
             true => {
@@ -260,7 +1419,7 @@ pub trait RemoteFs {
     fn iter_search(&mut self, dir: &Path, filter: &WildMatch) -> RemoteResult<Vec<File>> {
         let mut drained: Vec<File> = Vec::new();
         // Scan directory
-        match self.list_dir(dir) {
+        match self.list_dir_with(dir, &ListOptions::default()) {
             Ok(entries) => {
                 /* For each entry:
                 - if is dir: call iter_search with `dir`
@@ -286,14 +1445,1520 @@ pub trait RemoteFs {
     }
 }
 
+/// Move `src` to `dest` with a copy followed by the removal of `src`, applying the metadata of `src` to the copy,
+/// as [`RemoteFs::move_with_fallback`] does. Both paths must be absolute.
+/// If applying the metadata or removing `src` fails, the copy is removed, unless `src` has been partially removed
+pub(crate) fn move_by_copy<T>(fs: &mut T, src: &Path, dest: &Path) -> RemoteResult<()>
+where
+    T: RemoteFs + ?Sized,
+{
+    let root = fs.stat(src)?;
+    let mut entries = vec![];
+    if root.is_dir() {
+        entries = fs.walk(src, &WalkOptions::default())?;
+    }
+    entries.insert(0, root);
+    let total = entries.len();
+    fs.copy(src, dest)?;
+    rewrite_prefix(&mut entries, src, dest);
+    // apply metadata to children first, since updating them may change the modify time of their parent
+    let mut result = Ok(());
+    for entry in entries.into_iter().rev() {
+        trace!("Applying metadata to {}", entry.path().display());
+        result = fs.setstat(entry.path.as_path(), entry.metadata);
+        if result.is_err() {
+            break;
+        }
+    }
+    if result.is_ok() {
+        result = fs.remove_dir_all(src);
+    }
+    if let Err(err) = result {
+        // discard the copy only if `src` is still complete, so that no file gets lost
+        let intact = match fs.stat(src) {
+            Ok(root) if root.is_dir() => fs
+                .walk(src, &WalkOptions::default())
+                .map(|entries| entries.len() + 1 == total)
+                .unwrap_or(false),
+            Ok(_) => true,
+            Err(_) => false,
+        };
+        match intact {
+            true => {
+                debug!("Removing copy {}", dest.display());
+                if let Err(err) = fs.remove_dir_all(dest) {
+                    debug!("Failed to remove copy {}: {}", dest.display(), err);
+                }
+            }
+            false => debug!(
+                "Keeping copy {}, since {} has been partially removed",
+                dest.display(),
+                src.display()
+            ),
+        }
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Copy `reader` into `writer` as [`io::copy`] does, reading at most `buffer_size` bytes at a time
+pub(crate) fn copy_buffered<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    buffer_size: usize,
+) -> io::Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut buf = vec![0; buffer_size.max(1)];
+    let mut written = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(written),
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buf[..n])?;
+        written += n as u64;
+    }
+}
+
+/// Download `len` bytes of `remote` at `offset` with `client`, writing them to `writer` at the same offset
+fn download_range<T: RemoteFs>(
+    client: &mut T,
+    remote: &Path,
+    writer: &mut std::fs::File,
+    offset: u64,
+    len: u64,
+) -> RemoteResult<u64> {
+    writer
+        .seek(SeekFrom::Start(offset))
+        .map_err(RemoteError::io)?;
+    let mut stream = client.open_range(remote, offset, Some(len))?;
+    let sz = io::copy(&mut stream, writer)
+        .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+    client.on_read(stream)?;
+    trace!("Downloaded {} bytes at offset {}", sz, offset);
+    Ok(sz)
+}
+
+/// Get the path of the temporary file a download to `local` is written to, before being renamed to `local`
+fn partial_path(local: &Path) -> PathBuf {
+    let mut name = local.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    local.with_file_name(name)
+}
+
+/// Complete the download to the temporary file `part`, according to `result`:
+/// on success it is renamed to `local`, otherwise it is removed, leaving `local` untouched
+fn complete_partial(part: &Path, local: &Path, result: RemoteResult<u64>) -> RemoteResult<u64> {
+    let result = result.and_then(|sz| {
+        std::fs::rename(part, local)
+            .map(|()| sz)
+            .map_err(RemoteError::io)
+    });
+    if result.is_err() {
+        debug!("Removing partial download {}", part.display());
+        let _ = std::fs::remove_file(part);
+    }
+    result
+}
+
+/// Call `f` once for each unique path in `paths`, fanning the results out to the position of each path
+fn coalesce<R, F>(paths: &[PathBuf], mut f: F) -> Vec<R>
+where
+    R: Clone,
+    F: FnMut(&Path) -> R,
+{
+    let mut results: HashMap<&Path, R> = HashMap::with_capacity(paths.len());
+    paths
+        .iter()
+        .map(|path| {
+            results
+                .entry(path.as_path())
+                .or_insert_with(|| f(path))
+                .clone()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
 
+    use std::io::Cursor;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
     use super::*;
-    use crate::mock::MockRemoteFs;
+    use crate::fs::{HostKey, HostKeyDecision, MeteredFs};
+    use crate::mock::{MemoryFs, MockRemoteFs};
 
     #[test]
     fn should_be_able_to_create_trait_object() {
         let _: Box<dyn RemoteFs> = Box::new(MockRemoteFs {});
     }
+
+    #[test]
+    fn should_download_verified_file() {
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello");
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.txt");
+        assert_eq!(
+            client
+                .download_verified(
+                    Path::new("/a.txt"),
+                    local.as_path(),
+                    Some(String::from(
+                        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                    )),
+                )
+                .unwrap(),
+            5
+        );
+        assert_eq!(std::fs::read(local.as_path()).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn should_remove_downloaded_file_on_checksum_mismatch() {
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello");
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.txt");
+        let err = client
+            .download_verified(
+                Path::new("/a.txt"),
+                local.as_path(),
+                Some(String::from(
+                    "0000000000000000000000000000000000000000000000000000000000000000",
+                )),
+            )
+            .unwrap_err();
+        assert_eq!(err.kind, RemoteErrorType::ChecksumMismatch);
+        assert!(!local.exists());
+    }
+
+    #[test]
+    fn should_remove_downloaded_file_on_error() {
+        let mut client = MemoryFs::new();
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.txt");
+        let err = client
+            .download_verified(Path::new("/a.txt"), local.as_path(), None)
+            .unwrap_err();
+        assert_eq!(err.kind, RemoteErrorType::NoSuchFileOrDirectory);
+        assert!(!local.exists());
+        assert!(!temp.path().join("a.txt.part").exists());
+    }
+
+    #[test]
+    fn should_keep_existing_file_if_download_fails() {
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello");
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.txt");
+        std::fs::write(local.as_path(), b"existing").unwrap();
+        assert_eq!(
+            client
+                .download_verified(Path::new("/missing.txt"), local.as_path(), None)
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::NoSuchFileOrDirectory
+        );
+        assert_eq!(
+            client
+                .download_verified(
+                    Path::new("/a.txt"),
+                    local.as_path(),
+                    Some(String::from(
+                        "0000000000000000000000000000000000000000000000000000000000000000",
+                    )),
+                )
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::ChecksumMismatch
+        );
+        assert_eq!(std::fs::read(local.as_path()).unwrap(), b"existing");
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 1);
+        // replaced once verified
+        client
+            .download_verified(Path::new("/a.txt"), local.as_path(), None)
+            .unwrap();
+        assert_eq!(std::fs::read(local.as_path()).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn should_download_unverified_file_if_checksum_is_unsupported() {
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello");
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.txt");
+        assert_eq!(
+            client
+                .download_verified(Path::new("/a.txt"), local.as_path(), None)
+                .unwrap(),
+            5
+        );
+        assert!(local.exists());
+    }
+
+    fn times(client: &mut MemoryFs, path: &str) -> (SystemTime, SystemTime) {
+        let metadata = client.stat(Path::new(path)).unwrap().metadata;
+        (metadata.accessed.unwrap(), metadata.modified.unwrap())
+    }
+
+    #[test]
+    fn should_set_times() {
+        let atime = UNIX_EPOCH + Duration::from_secs(1000);
+        let mtime = UNIX_EPOCH + Duration::from_secs(2000);
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello");
+        let path = Path::new("/a.txt");
+        // both
+        client.set_times(path, Some(atime), Some(mtime)).unwrap();
+        assert_eq!(times(&mut client, "/a.txt"), (atime, mtime));
+        // accessed only; modified is now
+        let now = SystemTime::now();
+        client.set_times(path, Some(atime), None).unwrap();
+        let (accessed, modified) = times(&mut client, "/a.txt");
+        assert_eq!(accessed, atime);
+        assert!(modified >= now);
+        // modified only; accessed is now
+        let now = SystemTime::now();
+        client.set_times(path, None, Some(mtime)).unwrap();
+        let (accessed, modified) = times(&mut client, "/a.txt");
+        assert!(accessed >= now);
+        assert_eq!(modified, mtime);
+        // none; both are now
+        let now = SystemTime::now();
+        client.set_times(path, None, None).unwrap();
+        let (accessed, modified) = times(&mut client, "/a.txt");
+        assert!(accessed >= now);
+        assert!(modified >= now);
+        assert_eq!(accessed, modified);
+    }
+
+    #[test]
+    fn should_update_times() {
+        let atime = UNIX_EPOCH + Duration::from_secs(1000);
+        let mtime = UNIX_EPOCH + Duration::from_secs(2000);
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello");
+        let path = Path::new("/a.txt");
+        client.update_times(path, Some(atime), Some(mtime)).unwrap();
+        assert_eq!(times(&mut client, "/a.txt"), (atime, mtime));
+        let later = UNIX_EPOCH + Duration::from_secs(3000);
+        client.update_times(path, Some(later), None).unwrap();
+        assert_eq!(times(&mut client, "/a.txt"), (later, mtime));
+        client.update_times(path, None, Some(later)).unwrap();
+        assert_eq!(times(&mut client, "/a.txt"), (later, later));
+        client.update_times(path, None, None).unwrap();
+        assert_eq!(times(&mut client, "/a.txt"), (later, later));
+    }
+
+    #[test]
+    fn should_fail_setting_times_of_missing_file() {
+        let mut client = MemoryFs::new();
+        assert_eq!(
+            client
+                .set_times(Path::new("/a.txt"), None, None)
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::NoSuchFileOrDirectory
+        );
+    }
+
+    #[test]
+    fn should_resume_read_from_bytes_read() {
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello, world!");
+        let mut stream = client.open(Path::new("/a.txt")).unwrap();
+        let mut head = vec![0; 5];
+        stream.read_exact(&mut head).unwrap();
+        assert_eq!(stream.bytes_read(), 5);
+        // the stream dies here; resume from offset
+        let offset = stream.bytes_read();
+        drop(stream);
+        let mut stream = client
+            .open_range(Path::new("/a.txt"), offset, None)
+            .unwrap();
+        let mut tail = vec![];
+        stream.read_to_end(&mut tail).unwrap();
+        client.on_read(stream).unwrap();
+        head.extend(tail);
+        assert_eq!(head, b"hello, world!");
+    }
+
+    #[test]
+    fn should_open_file_range() {
+        let mut client = MemoryFs::new().with_file("/a.txt", b"hello, world!");
+        let mut stream = client.open_range(Path::new("/a.txt"), 7, Some(3)).unwrap();
+        assert!(!stream.seekable());
+        let mut buf = String::new();
+        stream.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "wor");
+        assert_eq!(stream.bytes_read(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "find")]
+    fn should_find_entries_sorted_by_name() {
+        let mut client = MockRemoteFs {};
+        assert_eq!(
+            client
+                .find("*.txt")
+                .unwrap()
+                .iter()
+                .map(|entry| entry.name())
+                .collect::<Vec<String>>(),
+            vec!["a.txt", "b.txt", "c.txt"]
+        );
+    }
+
+    #[test]
+    fn should_list_dir_sorted_by_name() {
+        let mut client = MockRemoteFs {};
+        let names = |entries: Vec<File>| -> Vec<String> {
+            entries.iter().map(|entry| entry.name()).collect()
+        };
+        assert_eq!(
+            names(client.list_dir(Path::new("/")).unwrap()),
+            vec!["c.txt", "a.txt", "b.txt"]
+        );
+        assert_eq!(
+            names(
+                client
+                    .list_dir_with(Path::new("/"), &ListOptions::default())
+                    .unwrap()
+            ),
+            vec!["a.txt", "b.txt", "c.txt"]
+        );
+        assert_eq!(
+            names(
+                client
+                    .list_dir_with(
+                        Path::new("/"),
+                        &ListOptions::default().order(ListOrder::Unsorted)
+                    )
+                    .unwrap()
+            ),
+            vec!["c.txt", "a.txt", "b.txt"]
+        );
+    }
+
+    fn walk_paths(client: &mut MemoryFs, options: WalkOptions) -> Vec<PathBuf> {
+        client
+            .walk(Path::new("/"), &options)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect()
+    }
+
+    #[test]
+    fn should_walk_with_depth_range() {
+        let mut client = MemoryFs::new()
+            .with_dir("/a")
+            .with_file("/a/1.txt", b"")
+            .with_dir("/a/b")
+            .with_file("/a/b/2.txt", b"")
+            .with_dir("/a/b/c")
+            .with_file("/a/b/c/3.txt", b"")
+            .with_file("/0.txt", b"");
+        assert_eq!(
+            walk_paths(&mut client, WalkOptions::default()),
+            vec![
+                PathBuf::from("/0.txt"),
+                PathBuf::from("/a"),
+                PathBuf::from("/a/1.txt"),
+                PathBuf::from("/a/b"),
+                PathBuf::from("/a/b/2.txt"),
+                PathBuf::from("/a/b/c"),
+                PathBuf::from("/a/b/c/3.txt"),
+            ]
+        );
+        assert_eq!(
+            walk_paths(&mut client, WalkOptions::default().max_depth(2)),
+            vec![
+                PathBuf::from("/0.txt"),
+                PathBuf::from("/a"),
+                PathBuf::from("/a/1.txt"),
+                PathBuf::from("/a/b"),
+            ]
+        );
+        assert_eq!(
+            walk_paths(
+                &mut client,
+                WalkOptions::default().min_depth(2).max_depth(3)
+            ),
+            vec![
+                PathBuf::from("/a/1.txt"),
+                PathBuf::from("/a/b"),
+                PathBuf::from("/a/b/2.txt"),
+                PathBuf::from("/a/b/c"),
+            ]
+        );
+        assert_eq!(
+            walk_paths(&mut client, WalkOptions::default().min_depth(4)),
+            vec![PathBuf::from("/a/b/c/3.txt")]
+        );
+        assert!(walk_paths(&mut client, WalkOptions::default().max_depth(0)).is_empty());
+    }
+
+    #[test]
+    fn should_download_file_in_parallel() {
+        let content: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let mut client = MemoryFs::new().with_file("/a.bin", &content);
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.bin");
+        assert_eq!(
+            client
+                .download_parallel(Path::new("/a.bin"), local.as_path(), 4)
+                .unwrap(),
+            1000
+        );
+        assert_eq!(std::fs::read(local.as_path()).unwrap(), content);
+        // more parts than bytes
+        let mut client = MemoryFs::new()
+            .with_file("/a.bin", &content)
+            .with_file("/b.bin", b"abc");
+        let local = temp.path().join("b.bin");
+        assert_eq!(
+            client
+                .download_parallel(Path::new("/b.bin"), local.as_path(), 8)
+                .unwrap(),
+            3
+        );
+        assert_eq!(std::fs::read(local.as_path()).unwrap(), b"abc");
+        // more parts than workers
+        let local = temp.path().join("c.bin");
+        assert_eq!(
+            client
+                .download_parallel(Path::new("/a.bin"), local.as_path(), 1000)
+                .unwrap(),
+            1000
+        );
+        assert_eq!(std::fs::read(local.as_path()).unwrap(), content);
+        assert_eq!(
+            client
+                .download_parallel(Path::new("/a.bin"), local.as_path(), 0)
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::IoError
+        );
+    }
+
+    #[test]
+    fn should_download_empty_file_in_parallel() {
+        let mut client = MemoryFs::new().with_file("/a.bin", b"");
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.bin");
+        assert_eq!(
+            client
+                .download_parallel(Path::new("/a.bin"), local.as_path(), 4)
+                .unwrap(),
+            0
+        );
+        assert!(local.exists());
+    }
+
+    #[test]
+    fn should_keep_existing_file_if_parallel_download_fails() {
+        let mut client = MemoryFs::new()
+            .with_file("/a.bin", b"hello")
+            .without_read_access();
+        let temp = TempDir::new().unwrap();
+        let local = temp.path().join("a.bin");
+        std::fs::write(local.as_path(), b"existing").unwrap();
+        assert_eq!(
+            client
+                .download_parallel(Path::new("/a.bin"), local.as_path(), 2)
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::PexError
+        );
+        assert_eq!(std::fs::read(local.as_path()).unwrap(), b"existing");
+        assert!(!temp.path().join("a.bin.part").exists());
+    }
+
+    #[test]
+    fn should_resolve_symlink_chain() {
+        let mut client = MemoryFs::new()
+            .with_dir("/tmp")
+            .with_file("/tmp/c.txt", b"hello")
+            .with_symlink("/tmp/b.txt", "c.txt")
+            .with_symlink("/a.txt", "/tmp/b.txt");
+        assert_eq!(
+            client.resolve_chain(Path::new("/a.txt")).unwrap(),
+            SymlinkChain {
+                steps: vec![
+                    PathBuf::from("/a.txt"),
+                    PathBuf::from("/tmp/b.txt"),
+                    PathBuf::from("/tmp/c.txt"),
+                ],
+                broken_at: None,
+            }
+        );
+        assert_eq!(
+            client.resolve_chain(Path::new("tmp/c.txt")).unwrap().steps,
+            vec![PathBuf::from("/tmp/c.txt")]
+        );
+    }
+
+    #[test]
+    fn should_report_broken_symlink_chain() {
+        let mut client = MemoryFs::new()
+            .with_symlink("/b.txt", "/c.txt")
+            .with_symlink("/a.txt", "/b.txt");
+        let chain = client.resolve_chain(Path::new("/a.txt")).unwrap();
+        assert_eq!(
+            chain.steps,
+            vec![
+                PathBuf::from("/a.txt"),
+                PathBuf::from("/b.txt"),
+                PathBuf::from("/c.txt"),
+            ]
+        );
+        assert_eq!(chain.broken_at, Some(PathBuf::from("/c.txt")));
+        assert_eq!(
+            chain.to_string(),
+            "/a.txt -> /b.txt -> /c.txt (/c.txt does not exist)"
+        );
+        assert_eq!(
+            client
+                .resolve_chain(Path::new("/missing"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::NoSuchFileOrDirectory
+        );
+        // loop
+        let mut client = MemoryFs::new()
+            .with_symlink("/a.txt", "/b.txt")
+            .with_symlink("/b.txt", "/a.txt");
+        assert_eq!(
+            client.resolve_chain(Path::new("/a.txt")).unwrap_err().kind,
+            RemoteErrorType::BadFile
+        );
+    }
+
+    #[test]
+    fn should_probe_path() {
+        let mut client = MemoryFs::new()
+            .with_dir("/tmp")
+            .with_file("/tmp/a.txt", b"hello")
+            .with_symlink("/tmp/b.txt", "a.txt");
+        assert_eq!(
+            client.probe_path(Path::new("/tmp")).unwrap(),
+            PathStatus::Dir
+        );
+        assert_eq!(
+            client.probe_path(Path::new("/tmp/a.txt")).unwrap(),
+            PathStatus::File
+        );
+        assert_eq!(
+            client.probe_path(Path::new("/tmp/b.txt")).unwrap(),
+            PathStatus::Symlink {
+                target: Some(PathBuf::from("a.txt"))
+            }
+        );
+        assert_eq!(
+            client.probe_path(Path::new("/tmp/c.txt")).unwrap(),
+            PathStatus::Missing
+        );
+        client.disconnect().unwrap();
+        assert_eq!(
+            client.probe_path(Path::new("/tmp")).unwrap_err().kind,
+            RemoteErrorType::NotConnected
+        );
+    }
+
+    #[test]
+    fn should_diff_directory_listing() {
+        let mut client = MemoryFs::new()
+            .with_dir("/tmp")
+            .with_file("/tmp/a.txt", b"a")
+            .with_file("/tmp/b.txt", b"b")
+            .with_file("/tmp/c.txt", b"c");
+        let previous = client.list_dir(Path::new("/tmp")).unwrap();
+        client.remove_file(Path::new("/tmp/a.txt")).unwrap();
+        client
+            .create_file(
+                Path::new("/tmp/b.txt"),
+                &Metadata::default(),
+                Box::new(Cursor::new(b"hello".to_vec())),
+            )
+            .unwrap();
+        client
+            .create_file(
+                Path::new("/tmp/d.txt"),
+                &Metadata::default(),
+                Box::new(Cursor::new(b"d".to_vec())),
+            )
+            .unwrap();
+        let diff = client.list_dir_diff(Path::new("/tmp"), &previous).unwrap();
+        let paths = |files: &[File]| -> Vec<PathBuf> {
+            files.iter().map(|file| file.path.clone()).collect()
+        };
+        assert_eq!(paths(&diff.added), vec![PathBuf::from("/tmp/d.txt")]);
+        assert_eq!(paths(&diff.removed), vec![PathBuf::from("/tmp/a.txt")]);
+        assert_eq!(paths(&diff.modified), vec![PathBuf::from("/tmp/b.txt")]);
+        assert_eq!(diff.modified[0].metadata().size, 5);
+    }
+
+    #[test]
+    fn should_not_support_named_streams_by_default() {
+        let mut client = MockRemoteFs {};
+        assert_eq!(
+            client
+                .open_named_stream(Path::new("/a.txt"), "Zone.Identifier")
+                .err()
+                .unwrap()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        assert_eq!(
+            client
+                .create_named_stream(Path::new("/a.txt"), "Zone.Identifier")
+                .err()
+                .unwrap()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+    }
+
+    #[test]
+    fn should_read_and_write_named_streams() {
+        let mut client = MemoryFs::new()
+            .with_file("/a.txt", b"hello")
+            .with_named_stream("/a.txt", "Zone.Identifier", b"[ZoneTransfer]");
+        let entry = client.stat(Path::new("/a.txt")).unwrap();
+        assert_eq!(
+            entry.metadata().streams.as_deref().unwrap(),
+            &[String::from("Zone.Identifier")]
+        );
+        let mut stream = client
+            .open_named_stream(Path::new("/a.txt"), "Zone.Identifier")
+            .unwrap();
+        let mut buf = String::new();
+        stream.read_to_string(&mut buf).unwrap();
+        client.on_read(stream).unwrap();
+        assert_eq!(buf, "[ZoneTransfer]");
+        // write a new stream
+        let mut stream = client
+            .create_named_stream(Path::new("/a.txt"), "com.apple.ResourceFork")
+            .unwrap();
+        stream.write_all(b"fork").unwrap();
+        client.on_written(stream).unwrap();
+        assert_eq!(
+            client
+                .stat(Path::new("/a.txt"))
+                .unwrap()
+                .metadata()
+                .streams
+                .as_deref()
+                .unwrap(),
+            &[
+                String::from("Zone.Identifier"),
+                String::from("com.apple.ResourceFork")
+            ]
+        );
+        assert!(client
+            .open_named_stream(Path::new("/a.txt"), "missing")
+            .is_err());
+        assert!(client
+            .stat(Path::new("/"))
+            .unwrap()
+            .metadata()
+            .streams
+            .is_none());
+    }
+
+    #[test]
+    fn should_fsync_file() {
+        let mut client = MockRemoteFs {};
+        assert_eq!(
+            client.fsync(Path::new("/a.txt")).unwrap_err().kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        let mut client = MemoryFs::new();
+        let mut stream = client
+            .create(Path::new("/a.txt"), &Metadata::default())
+            .unwrap();
+        stream.write_all(b"hello").unwrap();
+        client.on_written(stream).unwrap();
+        client.fsync(Path::new("/a.txt")).unwrap();
+        assert_eq!(client.fsync_calls(), vec![PathBuf::from("/a.txt")]);
+        assert_eq!(
+            client.fsync(Path::new("/b.txt")).unwrap_err().kind,
+            RemoteErrorType::NoSuchFileOrDirectory
+        );
+    }
+
+    #[test]
+    fn should_read_vectored_ranges() {
+        let mut client = MemoryFs::new().with_file("/a.zip", b"0123456789abcdefghij");
+        assert_eq!(
+            client
+                .read_vectored(Path::new("/a.zip"), &[(16, 4), (0, 2), (5, 3)])
+                .unwrap(),
+            vec![b"ghij".to_vec(), b"01".to_vec(), b"567".to_vec()]
+        );
+        // range exceeding end of file
+        assert_eq!(
+            client
+                .read_vectored(Path::new("/a.zip"), &[(18, 10), (30, 2)])
+                .unwrap(),
+            vec![b"ij".to_vec(), vec![]]
+        );
+    }
+
+    #[test]
+    fn should_remove_file_to_trash() {
+        let mut client = MemoryFs::new()
+            .with_dir("/home")
+            .with_file("/home/a.txt", b"first")
+            .with_file("/a.txt", b"second")
+            .with_file("/b", b"third");
+        assert_eq!(
+            client
+                .remove_file_to_trash(Path::new("/home/a.txt"), Path::new("/.trash"))
+                .unwrap(),
+            PathBuf::from("/.trash/a.txt")
+        );
+        assert!(!client.exists(Path::new("/home/a.txt")).unwrap());
+        assert_eq!(client.content("/.trash/a.txt").unwrap(), b"first");
+        // collision
+        assert_eq!(
+            client
+                .remove_file_to_trash(Path::new("/a.txt"), Path::new("/.trash"))
+                .unwrap(),
+            PathBuf::from("/.trash/a (1).txt")
+        );
+        assert!(!client.exists(Path::new("/a.txt")).unwrap());
+        assert_eq!(client.content("/.trash/a (1).txt").unwrap(), b"second");
+        assert_eq!(
+            client
+                .remove_file_to_trash(Path::new("/b"), Path::new("/.trash"))
+                .unwrap(),
+            PathBuf::from("/.trash/b")
+        );
+        // directories are not trashed
+        assert_eq!(
+            client
+                .remove_file_to_trash(Path::new("/home"), Path::new("/.trash"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::BadFile
+        );
+    }
+
+    #[test]
+    fn should_restore_working_directory_on_reconnect() {
+        let mut client = MemoryFs::new()
+            .with_dir("/tmp")
+            .with_file("/tmp/a.txt", b"a");
+        client.change_dir(Path::new("/tmp")).unwrap();
+        client.reconnect().unwrap();
+        assert!(client.is_connected());
+        assert_eq!(client.pwd().unwrap(), PathBuf::from("/tmp"));
+        assert!(client.exists(Path::new("a.txt")).unwrap());
+        // a plain connect resets the working directory
+        client.disconnect().unwrap();
+        client.connect().unwrap();
+        assert_eq!(client.pwd().unwrap(), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn should_restore_working_directory_on_reconnect_after_connection_dropped() {
+        let mut client = MemoryFs::new()
+            .with_dir("/tmp")
+            .with_file("/tmp/a.txt", b"a");
+        client.change_dir(Path::new("/tmp")).unwrap();
+        client.drop_connection();
+        assert!(client.pwd().is_err());
+        client.reconnect().unwrap();
+        assert!(client.is_connected());
+        assert_eq!(client.pwd().unwrap(), PathBuf::from("/tmp"));
+        assert!(client.exists(Path::new("a.txt")).unwrap());
+    }
+
+    #[test]
+    fn should_refresh_entries() {
+        let mut client = MemoryFs::new()
+            .with_file("/a.txt", b"a")
+            .with_file("/b.txt", b"b");
+        let mut entries = client.list_dir(Path::new("/")).unwrap();
+        client.remove_file(Path::new("/a.txt")).unwrap();
+        client
+            .create_file(
+                Path::new("/b.txt"),
+                &Metadata::default(),
+                Box::new(Cursor::new(b"hello".to_vec())),
+            )
+            .unwrap();
+        let results = client.refresh_entries(&mut entries);
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].as_ref().unwrap_err().kind,
+            RemoteErrorType::NoSuchFileOrDirectory
+        );
+        assert!(results[1].is_ok());
+        assert_eq!(entries[0].metadata().size, 1);
+        assert_eq!(entries[1].metadata().size, 5);
+    }
+
+    #[test]
+    fn should_tell_whether_paths_are_on_same_device() {
+        let mut client = MemoryFs::new()
+            .with_dir("/home")
+            .with_dir("/mnt")
+            .with_file("/home/a.txt", b"a")
+            .with_file("/mnt/b.txt", b"b")
+            .with_file("/c.txt", b"c")
+            .with_dev("/home", 1)
+            .with_dev("/home/a.txt", 1)
+            .with_dev("/mnt/b.txt", 2);
+        assert_eq!(
+            client
+                .same_device(Path::new("/home"), Path::new("/home/a.txt"))
+                .unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            client
+                .same_device(Path::new("/home/a.txt"), Path::new("/mnt/b.txt"))
+                .unwrap(),
+            Some(false)
+        );
+        assert_eq!(
+            client
+                .same_device(Path::new("/home/a.txt"), Path::new("/c.txt"))
+                .unwrap(),
+            None
+        );
+        assert!(client
+            .same_device(Path::new("/home/a.txt"), Path::new("/d.txt"))
+            .is_err());
+    }
+
+    #[test]
+    fn should_find_empty_files_and_dirs() {
+        let mut client = MemoryFs::new()
+            .with_dir("/a")
+            .with_file("/a/empty.txt", b"")
+            .with_file("/a/full.txt", b"hello")
+            .with_dir("/a/b")
+            .with_dir("/a/c")
+            .with_file("/a/c/empty.log", b"")
+            .with_dir("/d")
+            .with_symlink("/e", "/a");
+        let paths = |files: Vec<File>| -> Vec<PathBuf> {
+            files.into_iter().map(|file| file.path).collect()
+        };
+        assert_eq!(
+            paths(client.find_empty_files(Path::new("/")).unwrap()),
+            vec![
+                PathBuf::from("/a/c/empty.log"),
+                PathBuf::from("/a/empty.txt")
+            ]
+        );
+        assert_eq!(
+            paths(client.find_empty_dirs(Path::new("/")).unwrap()),
+            vec![PathBuf::from("/a/b"), PathBuf::from("/d")]
+        );
+        assert!(client.is_dir_empty(Path::new("/d")).unwrap());
+        assert!(!client.is_dir_empty(Path::new("/a")).unwrap());
+        assert_eq!(
+            client
+                .is_dir_empty(Path::new("/a/empty.txt"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::BadFile
+        );
+    }
+
+    #[test]
+    fn should_preserve_metadata_when_move_falls_back_to_copy() {
+        let modified = UNIX_EPOCH + Duration::from_secs(86400);
+        let mut client = MemoryFs::new()
+            .with_dir("/src")
+            .with_file("/src/a.txt", b"hello")
+            .with_dir("/mnt")
+            .without_mov();
+        client
+            .setstat(
+                Path::new("/src/a.txt"),
+                Metadata::default()
+                    .mode(UnixPex::from(0o600))
+                    .modified(modified),
+            )
+            .unwrap();
+        client
+            .setstat(
+                Path::new("/src"),
+                Metadata::default()
+                    .mode(UnixPex::from(0o700))
+                    .modified(modified),
+            )
+            .unwrap();
+        assert!(client
+            .mov(Path::new("/src"), Path::new("/mnt/dest"))
+            .is_err());
+        client
+            .move_with_fallback(Path::new("/src"), Path::new("/mnt/dest"))
+            .unwrap();
+        assert!(!client.exists(Path::new("/src")).unwrap());
+        let file = client.stat(Path::new("/mnt/dest/a.txt")).unwrap();
+        assert_eq!(client.content("/mnt/dest/a.txt").unwrap(), b"hello");
+        assert_eq!(file.metadata().mode, Some(UnixPex::from(0o600)));
+        assert_eq!(file.metadata().modified, Some(modified));
+        let dir = client.stat(Path::new("/mnt/dest")).unwrap();
+        assert_eq!(dir.metadata().mode, Some(UnixPex::from(0o700)));
+        assert_eq!(dir.metadata().modified, Some(modified));
+    }
+
+    #[test]
+    fn should_move_across_devices_with_fallback() {
+        let mut client = MemoryFs::new()
+            .with_dir("/src")
+            .with_file("/src/a.txt", b"hello")
+            .with_dir("/mnt")
+            .with_dev("/src", 1)
+            .with_dev("/mnt", 2);
+        assert_eq!(
+            client
+                .mov(Path::new("/src"), Path::new("/mnt/dest"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::CrossDevice
+        );
+        client
+            .move_with_fallback(Path::new("/src"), Path::new("/mnt/dest"))
+            .unwrap();
+        assert!(!client.exists(Path::new("/src")).unwrap());
+        assert_eq!(client.content("/mnt/dest/a.txt").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn should_not_fall_back_on_other_move_errors() {
+        let mut client = MeteredFs::new(
+            MemoryFs::new()
+                .with_dir("/src")
+                .with_file("/src/a.txt", b"hello"),
+        );
+        assert_eq!(
+            client
+                .move_with_fallback(Path::new("/src"), Path::new("/missing/dest"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::NoSuchFileOrDirectory
+        );
+        assert_eq!(client.metrics().operation("copy").count, 0);
+        assert!(client.exists(Path::new("/src/a.txt")).unwrap());
+    }
+
+    #[test]
+    fn should_remove_copy_if_move_fallback_fails() {
+        let mut client = MemoryFs::new()
+            .with_dir("/src")
+            .with_file("/src/a.txt", b"hello")
+            .with_dir("/mnt")
+            .without_mov()
+            .without_setstat();
+        assert_eq!(
+            client
+                .move_with_fallback(Path::new("/src"), Path::new("/mnt/dest"))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        assert!(!client.exists(Path::new("/mnt/dest")).unwrap());
+        assert_eq!(client.content("/src/a.txt").unwrap(), b"hello");
+    }
+
+    #[test]
+    fn should_summarize_directory_listing() {
+        let mut client = MemoryFs::new()
+            .with_dir("/tmp")
+            .with_file("/tmp/a.txt", b"hello")
+            .with_file("/tmp/b.txt", b"world!")
+            .with_dir("/tmp/c")
+            .with_file("/tmp/c/d.txt", b"not listed")
+            .with_symlink("/tmp/e", "/tmp/c");
+        assert_eq!(
+            client.list_dir_summary(Path::new("/tmp")).unwrap(),
+            DirSummary {
+                files: 3,
+                dirs: 1,
+                total_size: 11,
+            }
+        );
+    }
+
+    #[test]
+    fn should_verify_host_key_on_connect() {
+        let key = HostKey::new("localhost", "ssh-ed25519", b"key".to_vec());
+        let mut client = MemoryFs::new().with_host_key(key.clone());
+        client.disconnect().unwrap();
+        client.set_host_key_verifier(Box::new(|_| HostKeyDecision::Reject));
+        assert_eq!(
+            client.connect().unwrap_err().kind,
+            RemoteErrorType::HostKeyMismatch
+        );
+        assert!(!client.is_connected());
+        // accept and store
+        let fingerprint = key.fingerprint();
+        client.set_host_key_verifier(Box::new(move |key| {
+            match key.fingerprint() == fingerprint {
+                true => HostKeyDecision::AcceptAndStore,
+                false => HostKeyDecision::Reject,
+            }
+        }));
+        assert!(client.connect().is_ok());
+        assert_eq!(client.stored_host_keys(), vec![key]);
+        // ignored by default
+        let mut client = MockRemoteFs {};
+        client.set_host_key_verifier(Box::new(|_| HostKeyDecision::Reject));
+        assert!(client.connect().is_ok());
+    }
+
+    #[test]
+    fn should_read_lines() {
+        let mut client = MemoryFs::new().with_file("/a.log", b"one\ntwo\r\nthree\n");
+        let mut lines = client.read_lines(Path::new("/a.log")).unwrap();
+        let read: Vec<String> = lines.by_ref().map(|line| line.unwrap()).collect();
+        assert_eq!(read, vec!["one", "two", "three"]);
+        client.on_read(lines.into_inner()).unwrap();
+        assert!(client.read_lines(Path::new("/b.log")).is_err());
+    }
+
+    #[test]
+    fn should_check_uploaded_length() {
+        let mut client = MemoryFs::new();
+        assert_eq!(
+            client
+                .create_file_checked(
+                    Path::new("/a.txt"),
+                    &Metadata::default(),
+                    Box::new(Cursor::new(b"hello".to_vec())),
+                    5,
+                )
+                .unwrap(),
+            5
+        );
+        let err = client
+            .create_file_checked(
+                Path::new("/b.txt"),
+                &Metadata::default(),
+                Box::new(Cursor::new(b"hel".to_vec())),
+                5,
+            )
+            .unwrap_err();
+        assert_eq!(err.kind, RemoteErrorType::IncompleteTransfer);
+        assert_eq!(
+            err.msg.as_deref(),
+            Some("expected 5 bytes, but 3 bytes were written")
+        );
+    }
+
+    #[test]
+    fn should_coalesce_duplicate_paths() {
+        let mut client = MeteredFs::new(
+            MemoryFs::new()
+                .with_file("/a.txt", b"hello")
+                .with_dir("/dir"),
+        );
+        let paths = vec![
+            PathBuf::from("/a.txt"),
+            PathBuf::from("/dir"),
+            PathBuf::from("/a.txt"),
+            PathBuf::from("/missing"),
+            PathBuf::from("/dir/"),
+            PathBuf::from("/missing"),
+        ];
+        let stats = client.stat_many(&paths);
+        assert_eq!(stats.len(), 6);
+        assert_eq!(stats[0].as_ref().unwrap().path(), Path::new("/a.txt"));
+        assert_eq!(stats[2], stats[0]);
+        assert!(stats[1].as_ref().unwrap().is_dir());
+        assert_eq!(stats[4], stats[1]);
+        assert_eq!(
+            stats[3].as_ref().unwrap_err().kind,
+            RemoteErrorType::NoSuchFileOrDirectory
+        );
+        assert_eq!(stats[5], stats[3]);
+        assert_eq!(client.metrics().operation("stat_many").count, 1);
+
+        let exists: Vec<bool> = client
+            .exists_many(&paths)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(exists, vec![true, true, true, false, true, false]);
+        assert_eq!(client.metrics().operation("exists_many").count, 1);
+        // each unique path is requested once
+        let mut calls = 0;
+        let results = coalesce(&paths, |path| {
+            calls += 1;
+            path.to_path_buf()
+        });
+        assert_eq!(results.len(), 6);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn should_transfer_with_configured_buffer_size() {
+        let mut client = MemoryFs::new().with_buffer_size(4);
+        assert_eq!(client.buffer_size(), 4);
+        assert_eq!(
+            client
+                .create_file(
+                    Path::new("/a.txt"),
+                    &Metadata::default(),
+                    Box::new(Cursor::new(b"0123456789".to_vec())),
+                )
+                .unwrap(),
+            10
+        );
+        assert_eq!(client.write_sizes(), vec![4, 4, 2]);
+        assert_eq!(client.content("/a.txt").unwrap(), b"0123456789");
+    }
+
+    #[test]
+    fn should_buffer_streams() {
+        let mut client = MemoryFs::new();
+        assert_eq!(client.buffer_size(), DEFAULT_BUFFER_SIZE);
+        let mut stream = client
+            .create_buffered(Path::new("/a.txt"), &Metadata::default(), Some(4))
+            .unwrap();
+        for byte in b"0123456789" {
+            stream.write_all(&[*byte]).unwrap();
+        }
+        stream.flush().unwrap();
+        client.on_written(stream).unwrap();
+        assert_eq!(client.write_sizes(), vec![4, 4, 2]);
+
+        let mut stream = client.open_buffered(Path::new("/a.txt"), Some(4)).unwrap();
+        assert!(stream.seekable());
+        let mut buf = [0; 3];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"012");
+        stream.seek(SeekFrom::Start(8)).unwrap();
+        let mut rest = vec![];
+        stream.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"89");
+        client.on_read(stream).unwrap();
+    }
+
+    #[test]
+    fn should_find_entries_owned_by_user() {
+        let mut client = MemoryFs::new()
+            .with_dir("/a")
+            .with_file("/a/mine.txt", b"hello")
+            .with_file("/a/root.txt", b"hello")
+            .with_file("/a/unknown.txt", b"hello");
+        for (path, uid, gid) in [
+            ("/a", 1001, 100),
+            ("/a/mine.txt", 1001, 1001),
+            ("/a/root.txt", 0, 0),
+        ] {
+            client
+                .setstat(Path::new(path), Metadata::default().uid(uid).gid(gid))
+                .unwrap();
+        }
+        let paths = |files: Vec<File>| -> Vec<PathBuf> {
+            files.into_iter().map(|file| file.path).collect()
+        };
+        assert_eq!(
+            paths(client.find_owned_by(Path::new("/a"), 1001, false).unwrap()),
+            vec![PathBuf::from("/a/mine.txt")]
+        );
+        assert_eq!(
+            paths(client.find_owned_by(Path::new("/a"), 1001, true).unwrap()),
+            vec![
+                PathBuf::from("/a/mine.txt"),
+                PathBuf::from("/a/unknown.txt")
+            ]
+        );
+        assert_eq!(
+            paths(
+                client
+                    .find_filtered(Path::new("/"), &EntryFilter::GroupIs(100))
+                    .unwrap()
+            ),
+            vec![PathBuf::from("/a")]
+        );
+    }
+
+    #[test]
+    fn should_plan_and_execute_removal() {
+        let mut client = MemoryFs::new()
+            .with_dir("/a")
+            .with_file("/a/1.txt", b"hello")
+            .with_dir("/a/b")
+            .with_file("/a/b/2.txt", b"hello")
+            .with_symlink("/a/c", "/d")
+            .with_dir("/d")
+            .with_file("/d/3.txt", b"hello");
+        let plan = client.plan_remove_dir_all(Path::new("/a")).unwrap();
+        assert_eq!(
+            plan,
+            vec![
+                PathBuf::from("/a/c"),
+                PathBuf::from("/a/b/2.txt"),
+                PathBuf::from("/a/b"),
+                PathBuf::from("/a/1.txt"),
+                PathBuf::from("/a"),
+            ]
+        );
+        assert!(client.exists(Path::new("/a/b/2.txt")).unwrap());
+        client.execute_removal_plan(&plan).unwrap();
+        for path in plan.iter() {
+            assert!(!client.exists(path).unwrap());
+        }
+        assert!(client.exists(Path::new("/d/3.txt")).unwrap());
+        // plan of a file
+        assert_eq!(
+            client.plan_remove_dir_all(Path::new("/d/3.txt")).unwrap(),
+            vec![PathBuf::from("/d/3.txt")]
+        );
+    }
+
+    #[test]
+    fn should_find_broken_symlinks() {
+        let mut client = MemoryFs::new()
+            .with_dir("/a")
+            .with_file("/a/target.txt", b"hello")
+            .with_symlink("/a/valid", "target.txt")
+            .with_symlink("/a/broken", "/a/missing.txt")
+            .with_dir("/a/b")
+            .with_symlink("/a/b/chain", "/a/broken");
+        let broken: Vec<PathBuf> = client
+            .find_broken_symlinks(Path::new("/a"))
+            .unwrap()
+            .into_iter()
+            .map(|file| file.path)
+            .collect();
+        assert_eq!(
+            broken,
+            vec![PathBuf::from("/a/b/chain"), PathBuf::from("/a/broken")]
+        );
+    }
+
+    #[test]
+    fn should_create_dir_with_exact_mode() {
+        let mut client = MemoryFs::new().with_umask(0o027);
+        client
+            .create_dir(Path::new("/a"), UnixPex::from(0o770))
+            .unwrap();
+        assert_eq!(
+            client.stat(Path::new("/a")).unwrap().metadata.mode,
+            Some(UnixPex::from(0o750))
+        );
+        client
+            .create_dir_exact(Path::new("/b"), UnixPex::from(0o770))
+            .unwrap();
+        assert_eq!(
+            client.stat(Path::new("/b")).unwrap().metadata.mode,
+            Some(UnixPex::from(0o770))
+        );
+        assert_eq!(
+            client
+                .create_dir_exact(Path::new("/b"), UnixPex::from(0o770))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::DirectoryAlreadyExists
+        );
+    }
+
+    #[test]
+    fn should_remove_dir_if_exact_mode_cannot_be_applied() {
+        let mut client = MemoryFs::new().with_umask(0o027).without_setstat();
+        assert_eq!(
+            client
+                .create_dir_exact(Path::new("/a"), UnixPex::from(0o770))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        assert!(!client.exists(Path::new("/a")).unwrap());
+    }
+
+    #[test]
+    fn should_resume_dir_copy_without_setstat() {
+        let mut client = MemoryFs::new()
+            .with_dir("/src")
+            .with_file("/src/a.txt", b"hello")
+            .with_dir("/src/b")
+            .with_file("/src/b/c.txt", b"world")
+            .without_setstat();
+        assert_eq!(
+            client
+                .copy_dir_resume(Path::new("/src"), Path::new("/dest"))
+                .unwrap(),
+            2
+        );
+        assert_eq!(client.content("/dest/a.txt").unwrap(), b"hello");
+        assert_eq!(client.content("/dest/b/c.txt").unwrap(), b"world");
+    }
+
+    #[test]
+    fn should_resume_interrupted_dir_copy() {
+        let mut client = MeteredFs::new(
+            MemoryFs::new()
+                .with_dir("/src")
+                .with_file("/src/a.txt", b"hello")
+                .with_dir("/src/b")
+                .with_file("/src/b/c.txt", b"world")
+                .with_file("/src/b/d.txt", b"!"),
+        );
+        let modified = UNIX_EPOCH + Duration::from_secs(86400);
+        for path in ["/src/a.txt", "/src/b/c.txt", "/src/b/d.txt"] {
+            client
+                .setstat(Path::new(path), Metadata::default().modified(modified))
+                .unwrap();
+        }
+        // interrupted copy: "/dest/b/c.txt" has been copied, "/dest/a.txt" is partial
+        client
+            .create_dir(Path::new("/dest"), UnixPex::from(0o755))
+            .unwrap();
+        client
+            .create_dir(Path::new("/dest/b"), UnixPex::from(0o755))
+            .unwrap();
+        client
+            .copy(Path::new("/src/b/c.txt"), Path::new("/dest/b/c.txt"))
+            .unwrap();
+        client
+            .setstat(
+                Path::new("/dest/b/c.txt"),
+                Metadata::default().modified(modified),
+            )
+            .unwrap();
+        client
+            .create_file(
+                Path::new("/dest/a.txt"),
+                &Metadata::default(),
+                Box::new(Cursor::new(b"he".to_vec())),
+            )
+            .unwrap();
+        client.reset();
+
+        assert_eq!(
+            client
+                .copy_dir_resume(Path::new("/src"), Path::new("/dest"))
+                .unwrap(),
+            2
+        );
+        assert_eq!(client.metrics().operation("copy").count, 2);
+        let client = client.into_inner();
+        assert_eq!(client.content("/dest/a.txt").unwrap(), b"hello");
+        assert_eq!(client.content("/dest/b/c.txt").unwrap(), b"world");
+        assert_eq!(client.content("/dest/b/d.txt").unwrap(), b"!");
+        let mut client = MeteredFs::new(client);
+        assert_eq!(
+            client
+                .copy_dir_resume(Path::new("/src"), Path::new("/dest"))
+                .unwrap(),
+            0
+        );
+        assert_eq!(client.metrics().operation("copy").count, 0);
+    }
+
+    #[test]
+    fn should_exec_with_stdin() {
+        let mut client = MockRemoteFs {};
+        assert_eq!(
+            client
+                .exec_with_stdin("cat > /a.txt", Box::new(Cursor::new(b"hello".to_vec())))
+                .unwrap_err()
+                .kind,
+            RemoteErrorType::UnsupportedFeature
+        );
+        let mut client = MemoryFs::new();
+        let output = client
+            .exec_with_stdin("cat > /a.txt", Box::new(Cursor::new(b"hello".to_vec())))
+            .unwrap();
+        assert!(output.is_success());
+        assert_eq!(output.stdin_written, 5);
+        assert_eq!(client.content("/a.txt").unwrap(), b"hello");
+        // stdin closed early
+        let output = client
+            .exec_with_stdin(
+                "head -c 3 > /b.txt",
+                Box::new(Cursor::new(b"hello world".to_vec())),
+            )
+            .unwrap();
+        assert!(output.is_success());
+        assert_eq!(output.stdin_written, 3);
+        assert_eq!(client.content("/b.txt").unwrap(), b"hel");
+    }
+
+    #[test]
+    fn should_resolve_symlinks_in_listing() {
+        let mut client = MeteredFs::new(
+            MemoryFs::new()
+                .with_dir("/a")
+                .with_file("/a/b.txt", b"hello")
+                .with_symlink("/a/c", "b.txt")
+                .with_symlink("/a/d", "/tmp")
+                .without_symlink_targets_in_listing(),
+        );
+        let targets = |entries: Vec<File>| -> Vec<Option<PathBuf>> {
+            entries
+                .into_iter()
+                .map(|entry| entry.metadata.symlink)
+                .collect()
+        };
+        assert_eq!(
+            targets(
+                client
+                    .list_dir_with(Path::new("/a"), &ListOptions::default())
+                    .unwrap()
+            ),
+            vec![None, None, None]
+        );
+        assert_eq!(client.metrics().operation("read_link").count, 0);
+        assert_eq!(
+            targets(
+                client
+                    .list_dir_with(
+                        Path::new("/a"),
+                        &ListOptions::default().resolve_symlinks(true)
+                    )
+                    .unwrap()
+            ),
+            vec![
+                None,
+                Some(PathBuf::from("b.txt")),
+                Some(PathBuf::from("/tmp"))
+            ]
+        );
+        assert_eq!(client.metrics().operation("read_links").count, 1);
+        assert_eq!(
+            client.read_link(Path::new("/a/b.txt")).unwrap_err().kind,
+            RemoteErrorType::BadFile
+        );
+    }
+
+    #[test]
+    fn should_leave_unreadable_symlink_unresolved_in_listing() {
+        let mut client = MemoryFs::new()
+            .with_dir("/a")
+            .with_symlink("/a/b", "/tmp")
+            .with_unreadable_symlink("/a/c")
+            .without_symlink_targets_in_listing();
+        let entries = client
+            .list_dir_with(
+                Path::new("/a"),
+                &ListOptions::default().resolve_symlinks(true),
+            )
+            .unwrap();
+        assert_eq!(
+            entries
+                .into_iter()
+                .map(|entry| (entry.name(), entry.metadata.symlink))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("b"), Some(PathBuf::from("/tmp"))),
+                (String::from("c"), None)
+            ]
+        );
+    }
 }
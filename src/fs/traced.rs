@@ -0,0 +1,413 @@
+//! ## Traced
+//!
+//! a `RemoteFs` wrapper which logs each operation performed
+
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use log::Level;
+
+use super::{
+    ExecOutput, File, HostKeyVerifier, Metadata, ReadStream, RemoteFs, RemoteResult, UnixPex,
+    Welcome, WriteStream,
+};
+
+/// Log target used by [`TracingFs`]
+pub const TRACING_FS_TARGET: &str = "remotefs::ops";
+
+/// A [`RemoteFs`] wrapper which logs each operation, with its path, duration and outcome,
+/// through the `log` facade under the [`TRACING_FS_TARGET`] target.
+/// Despite its name, it emits plain `log` records: no `tracing` spans or events are created.
+///
+/// Successful operations are logged at `debug` level, failed operations at `warn` level.
+/// File contents and the commands passed to [`RemoteFs::exec`] are never logged, since they may hold secrets.
+/// The duration of a stream operation only covers opening the stream.
+///
+/// Methods composed of other operations (e.g. [`RemoteFs::set_times`]) are run on the wrapper,
+/// so each underlying operation is logged.
+pub struct TracingFs<T: RemoteFs> {
+    inner: T,
+}
+
+impl<T: RemoteFs> TracingFs<T> {
+    /// Wrap `inner` into a new `TracingFs`
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Get a reference to the inner file system
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner file system
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the inner file system
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Run `f` on the inner file system, logging it as operation `name` on `subject`
+    fn trace<R, F>(&mut self, name: &str, subject: impl fmt::Display, f: F) -> RemoteResult<R>
+    where
+        F: FnOnce(&mut T) -> RemoteResult<R>,
+    {
+        let started = Instant::now();
+        let result = f(&mut self.inner);
+        let elapsed = started.elapsed();
+        let subject = subject.to_string();
+        let sep = if subject.is_empty() { "" } else { " " };
+        match &result {
+            Ok(_) => log!(
+                target: TRACING_FS_TARGET,
+                Level::Debug,
+                "{name}{sep}{subject}: ok after {elapsed:?}"
+            ),
+            Err(err) => log!(
+                target: TRACING_FS_TARGET,
+                Level::Warn,
+                "{name}{sep}{subject}: {err} after {elapsed:?}"
+            ),
+        }
+        result
+    }
+
+    /// Run `f` on the inner file system, logging it as a single operation `name` on `paths`
+    fn trace_many<R, F>(&mut self, name: &str, paths: &[PathBuf], f: F) -> Vec<RemoteResult<R>>
+    where
+        F: FnOnce(&mut T) -> Vec<RemoteResult<R>>,
+    {
+        let started = Instant::now();
+        let results = f(&mut self.inner);
+        let elapsed = started.elapsed();
+        let paths = paths.len();
+        match results.iter().filter(|result| result.is_err()).count() {
+            0 => log!(
+                target: TRACING_FS_TARGET,
+                Level::Debug,
+                "{name} {paths} paths: ok after {elapsed:?}"
+            ),
+            failed => log!(
+                target: TRACING_FS_TARGET,
+                Level::Warn,
+                "{name} {paths} paths: {failed} failed after {elapsed:?}"
+            ),
+        }
+        results
+    }
+}
+
+impl<T: RemoteFs> RemoteFs for TracingFs<T> {
+    fn connect(&mut self) -> RemoteResult<Welcome> {
+        self.trace("connect", "", |fs| fs.connect())
+    }
+
+    fn set_host_key_verifier(&mut self, verifier: HostKeyVerifier) {
+        self.inner.set_host_key_verifier(verifier)
+    }
+
+    fn disconnect(&mut self) -> RemoteResult<()> {
+        self.trace("disconnect", "", |fs| fs.disconnect())
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn pwd(&mut self) -> RemoteResult<PathBuf> {
+        self.trace("pwd", "", |fs| fs.pwd())
+    }
+
+    fn change_dir(&mut self, dir: &Path) -> RemoteResult<PathBuf> {
+        self.trace("change_dir", dir.display(), |fs| fs.change_dir(dir))
+    }
+
+    fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>> {
+        self.trace("list_dir", path.display(), |fs| fs.list_dir(path))
+    }
+
+    fn stat(&mut self, path: &Path) -> RemoteResult<File> {
+        self.trace("stat", path.display(), |fs| fs.stat(path))
+    }
+
+    fn stat_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<File>> {
+        self.trace_many("stat_many", paths, |fs| fs.stat_many(paths))
+    }
+
+    fn setstat(&mut self, path: &Path, metadata: Metadata) -> RemoteResult<()> {
+        self.trace("setstat", path.display(), |fs| fs.setstat(path, metadata))
+    }
+
+    fn exists(&mut self, path: &Path) -> RemoteResult<bool> {
+        self.trace("exists", path.display(), |fs| fs.exists(path))
+    }
+
+    fn exists_many(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<bool>> {
+        self.trace_many("exists_many", paths, |fs| fs.exists_many(paths))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> RemoteResult<()> {
+        self.trace("remove_file", path.display(), |fs| fs.remove_file(path))
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> RemoteResult<()> {
+        self.trace("remove_dir", path.display(), |fs| fs.remove_dir(path))
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> RemoteResult<()> {
+        self.trace("remove_dir_all", path.display(), |fs| {
+            fs.remove_dir_all(path)
+        })
+    }
+
+    fn create_dir(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()> {
+        self.trace("create_dir", path.display(), |fs| fs.create_dir(path, mode))
+    }
+
+    fn read_link(&mut self, path: &Path) -> RemoteResult<PathBuf> {
+        self.trace("read_link", path.display(), |fs| fs.read_link(path))
+    }
+
+    fn read_links(&mut self, paths: &[PathBuf]) -> Vec<RemoteResult<PathBuf>> {
+        self.trace_many("read_links", paths, |fs| fs.read_links(paths))
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> RemoteResult<()> {
+        let subject = format!("{} -> {}", path.display(), target.display());
+        self.trace("symlink", subject, |fs| fs.symlink(path, target))
+    }
+
+    fn copy(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        let subject = format!("{} -> {}", src.display(), dest.display());
+        self.trace("copy", subject, |fs| fs.copy(src, dest))
+    }
+
+    fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        let subject = format!("{} -> {}", src.display(), dest.display());
+        self.trace("mov", subject, |fs| fs.mov(src, dest))
+    }
+
+    fn exec(&mut self, cmd: &str) -> RemoteResult<(u32, String)> {
+        self.trace("exec", "", |fs| fs.exec(cmd))
+    }
+
+    fn exec_with_stdin(
+        &mut self,
+        cmd: &str,
+        stdin: Box<dyn Read + Send>,
+    ) -> RemoteResult<ExecOutput> {
+        self.trace("exec_with_stdin", "", |fs| fs.exec_with_stdin(cmd, stdin))
+    }
+
+    fn append(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.trace("append", path.display(), |fs| fs.append(path, metadata))
+    }
+
+    fn create(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.trace("create", path.display(), |fs| fs.create(path, metadata))
+    }
+
+    fn open(&mut self, path: &Path) -> RemoteResult<ReadStream> {
+        self.trace("open", path.display(), |fs| fs.open(path))
+    }
+
+    fn open_range(
+        &mut self,
+        path: &Path,
+        offset: u64,
+        len: Option<u64>,
+    ) -> RemoteResult<ReadStream> {
+        self.trace("open_range", path.display(), |fs| {
+            fs.open_range(path, offset, len)
+        })
+    }
+
+    fn open_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<ReadStream> {
+        let subject = format!("{}:{}", path.display(), name);
+        self.trace("open_named_stream", subject, |fs| {
+            fs.open_named_stream(path, name)
+        })
+    }
+
+    fn create_named_stream(&mut self, path: &Path, name: &str) -> RemoteResult<WriteStream> {
+        let subject = format!("{}:{}", path.display(), name);
+        self.trace("create_named_stream", subject, |fs| {
+            fs.create_named_stream(path, name)
+        })
+    }
+
+    fn on_written(&mut self, writable: WriteStream) -> RemoteResult<()> {
+        self.trace("on_written", "", |fs| fs.on_written(writable))
+    }
+
+    fn on_read(&mut self, readable: ReadStream) -> RemoteResult<()> {
+        self.trace("on_read", "", |fs| fs.on_read(readable))
+    }
+
+    fn buffer_size(&self) -> usize {
+        self.inner.buffer_size()
+    }
+
+    fn last_working_dir(&self) -> Option<PathBuf> {
+        self.inner.last_working_dir()
+    }
+
+    fn fsync(&mut self, path: &Path) -> RemoteResult<()> {
+        self.trace("fsync", path.display(), |fs| fs.fsync(path))
+    }
+
+    fn append_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        self.trace("append_file", path.display(), |fs| {
+            fs.append_file(path, metadata, reader)
+        })
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read + Send>,
+    ) -> RemoteResult<u64> {
+        self.trace("create_file", path.display(), |fs| {
+            fs.create_file(path, metadata, reader)
+        })
+    }
+
+    fn open_file(&mut self, src: &Path) -> RemoteResult<(u64, Vec<u8>)> {
+        self.trace("open_file", src.display(), |fs| fs.open_file(src))
+    }
+
+    fn checksum(&mut self, path: &Path) -> RemoteResult<String> {
+        self.trace("checksum", path.display(), |fs| fs.checksum(path))
+    }
+
+    #[cfg(feature = "find")]
+    fn find(&mut self, search: &str) -> RemoteResult<Vec<File>> {
+        self.trace("find", search, |fs| fs.find(search))
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(feature = "no-log"))]
+mod test {
+
+    use std::io::Cursor;
+    use std::sync::{Mutex, OnceLock};
+    use std::thread::{self, ThreadId};
+
+    use log::{LevelFilter, Log, Metadata as LogMetadata, Record};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mock::MemoryFs;
+
+    /// A logger which captures the records emitted by `TracingFs`, with the thread which emitted them
+    struct CapturingLogger {
+        records: Mutex<Vec<(ThreadId, Level, String)>>,
+    }
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, metadata: &LogMetadata) -> bool {
+            metadata.target() == TRACING_FS_TARGET
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                self.records.lock().unwrap().push((
+                    thread::current().id(),
+                    record.level(),
+                    record.args().to_string(),
+                ));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    /// Install the capturing logger, unless another logger has already been installed.
+    /// Returns whether the capturing logger is in use
+    fn install_logger() -> bool {
+        static INSTALLED: OnceLock<bool> = OnceLock::new();
+        *INSTALLED.get_or_init(|| match log::set_logger(&LOGGER) {
+            Ok(()) => {
+                log::set_max_level(LevelFilter::Trace);
+                true
+            }
+            Err(_) => false,
+        })
+    }
+
+    /// Get the records captured on the current thread, without the duration
+    fn captured() -> Vec<(Level, String)> {
+        let current = thread::current().id();
+        LOGGER
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(thread, _, _)| *thread == current)
+            .map(|(_, level, msg)| (*level, msg.split(" after ").next().unwrap().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn should_log_operations() {
+        if !install_logger() {
+            // records can't be captured if another logger is in use
+            return;
+        }
+        let mut client = TracingFs::new(MemoryFs::new().with_file("/a.txt", b"hello"));
+        assert!(client.stat(Path::new("/a.txt")).is_ok());
+        assert!(client.stat(Path::new("/b.txt")).is_err());
+        let paths = [PathBuf::from("/a.txt"), PathBuf::from("/b.txt")];
+        assert_eq!(client.stat_many(&paths).len(), 2);
+        assert_eq!(client.exists_many(&paths[..1]).len(), 1);
+        assert_eq!(
+            client
+                .create_file(
+                    Path::new("/c.txt"),
+                    &Metadata::default(),
+                    Box::new(Cursor::new(b"secret".to_vec())),
+                )
+                .unwrap(),
+            6
+        );
+        assert!(client.mov(Path::new("/c.txt"), Path::new("/d.txt")).is_ok());
+        let _ = client.exec("echo password");
+
+        let records = captured();
+        assert_eq!(
+            records,
+            vec![
+                (Level::Debug, String::from("stat /a.txt: ok")),
+                (
+                    Level::Warn,
+                    String::from("stat /b.txt: no such file or directory")
+                ),
+                (Level::Warn, String::from("stat_many 2 paths: 1 failed")),
+                (Level::Debug, String::from("exists_many 1 paths: ok")),
+                (Level::Debug, String::from("create_file /c.txt: ok")),
+                (Level::Debug, String::from("mov /c.txt -> /d.txt: ok")),
+                (Level::Warn, String::from("exec: unsupported feature")),
+            ]
+        );
+        assert!(records
+            .iter()
+            .all(|(_, msg)| !msg.contains("secret") && !msg.contains("password")));
+        assert_eq!(client.into_inner().content("/d.txt").unwrap(), b"secret");
+    }
+}
@@ -0,0 +1,261 @@
+//! ## Tree
+//!
+//! utilities to arrange flat lists of files into trees
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{File, FileType, Metadata};
+
+/// A node in a file tree built with [`build_tree`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeNode {
+    /// The file represented by this node
+    pub entry: File,
+    /// Nodes contained in this node
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    /// Find the node at `path` in this tree
+    pub fn find(&self, path: &Path) -> Option<&TreeNode> {
+        if self.entry.path() == path {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(path))
+    }
+}
+
+/// Build a tree out of a flat list of `entries`, such as the result of a recursive walk, rooted at `root`.
+///
+/// Each entry is attached to its nearest ancestor in `entries`.
+/// Entries whose ancestors are not in `entries` (or which are not under `root` at all) are attached to the root node.
+/// If `root` is not in `entries`, the root node is a directory with default metadata.
+/// Children keep the order they have in `entries`.
+pub fn build_tree(entries: Vec<File>, root: &Path) -> TreeNode {
+    let mut root_entry = File {
+        path: root.to_path_buf(),
+        metadata: Metadata::default().file_type(FileType::Directory),
+    };
+    let mut entries: Vec<(usize, File)> = entries
+        .into_iter()
+        .filter_map(|entry| match entry.path() == root {
+            true => {
+                root_entry = entry;
+                None
+            }
+            false => Some(entry),
+        })
+        .enumerate()
+        .collect();
+    // sort by depth, so that ancestors are always indexed before their descendants
+    entries.sort_by_key(|(_, entry)| entry.path().components().count());
+    // index 0 is the root; nodes are stored in an arena and then assembled
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+    let mut children: Vec<Vec<usize>> = vec![vec![]; entries.len() + 1];
+    for (i, (_, entry)) in entries.iter().enumerate() {
+        let parent = entry
+            .path()
+            .ancestors()
+            .skip(1)
+            .take_while(|ancestor| *ancestor != root)
+            .find_map(|ancestor| index.get(ancestor).copied())
+            .unwrap_or(0);
+        children[parent].push(i + 1);
+        index.insert(entry.path.clone(), i + 1);
+    }
+    // restore the original order of entries among siblings
+    for siblings in children.iter_mut() {
+        siblings.sort_by_key(|i| entries[i - 1].0);
+    }
+    let mut nodes: Vec<Option<File>> = std::iter::once(Some(root_entry))
+        .chain(entries.into_iter().map(|(_, entry)| Some(entry)))
+        .collect();
+    assemble(0, &mut nodes, &children)
+}
+
+/// Differences between two listings of files, as returned by [`compare_trees`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TreeDiff {
+    /// Files which exist only in the current listing
+    pub added: Vec<File>,
+    /// Files which exist only in the previous listing
+    pub removed: Vec<File>,
+    /// Files which exist in both listings, but whose content changed (see [`Metadata::content_eq`]).
+    /// Files are reported as they are in the current listing
+    pub modified: Vec<File>,
+}
+
+impl TreeDiff {
+    /// Returns whether the listings are the same
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Compare the `previous` and `current` listings of files, matching files by path.
+///
+/// Added and modified files keep the order they have in `current`, removed files the order they have in `previous`.
+pub fn compare_trees(previous: &[File], current: &[File]) -> TreeDiff {
+    let previous_index: HashMap<&Path, &File> =
+        previous.iter().map(|file| (file.path(), file)).collect();
+    let current_index: HashMap<&Path, &File> =
+        current.iter().map(|file| (file.path(), file)).collect();
+    let mut diff = TreeDiff::default();
+    for file in current {
+        match previous_index.get(file.path()) {
+            None => diff.added.push(file.clone()),
+            Some(old) if !old.metadata().content_eq(file.metadata()) => {
+                diff.modified.push(file.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = previous
+        .iter()
+        .filter(|file| !current_index.contains_key(file.path()))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Relocate `entries`, replacing the leading `from` of their paths with `to`,
+/// such as `/src/docs/a.txt` to `/dst/docs/a.txt` with `from = /src` and `to = /dst`.
+/// An entry at `from` itself is moved to `to`, so its name changes accordingly.
+/// Entries which are not under `from` are left untouched.
+pub fn rewrite_prefix(entries: &mut [File], from: &Path, to: &Path) {
+    for entry in entries.iter_mut() {
+        let rewritten = match entry.path().strip_prefix(from) {
+            Ok(rel) if rel.as_os_str().is_empty() => to.to_path_buf(),
+            Ok(rel) => to.join(rel),
+            Err(_) => continue,
+        };
+        entry.path = rewritten;
+    }
+}
+
+/// Recursively assemble the node at index `i` from the arena
+fn assemble(i: usize, nodes: &mut [Option<File>], children: &[Vec<usize>]) -> TreeNode {
+    TreeNode {
+        entry: nodes[i].take().unwrap(),
+        children: children[i]
+            .iter()
+            .map(|child| assemble(*child, nodes, children))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn entry(path: &str, file_type: FileType) -> File {
+        File {
+            path: PathBuf::from(path),
+            metadata: Metadata::default().file_type(file_type),
+        }
+    }
+
+    fn names(node: &TreeNode) -> Vec<String> {
+        node.children
+            .iter()
+            .map(|child| child.entry.name())
+            .collect()
+    }
+
+    #[test]
+    fn should_build_tree_from_flat_listing() {
+        let entries = vec![
+            entry("/home/a.txt", FileType::File),
+            entry("/home/docs/b.txt", FileType::File),
+            entry("/home/docs", FileType::Directory),
+            entry("/home/docs/old", FileType::Directory),
+            entry("/home/docs/old/c.txt", FileType::File),
+        ];
+        let tree = build_tree(entries, Path::new("/home"));
+        assert_eq!(tree.entry.path(), Path::new("/home"));
+        assert!(tree.entry.is_dir());
+        assert_eq!(names(&tree), vec!["a.txt", "docs"]);
+        let docs = tree.find(Path::new("/home/docs")).unwrap();
+        assert_eq!(names(docs), vec!["b.txt", "old"]);
+        let old = tree.find(Path::new("/home/docs/old")).unwrap();
+        assert_eq!(names(old), vec!["c.txt"]);
+        assert!(tree
+            .find(Path::new("/home/docs/old/c.txt"))
+            .unwrap()
+            .children
+            .is_empty());
+    }
+
+    #[test]
+    fn should_attach_orphans_to_nearest_ancestor() {
+        let entries = vec![
+            entry("/home", FileType::Directory),
+            entry("/home/docs", FileType::Directory),
+            entry("/home/docs/old/deep/c.txt", FileType::File),
+            entry("/home/music/d.mp3", FileType::File),
+            entry("/tmp/e.txt", FileType::File),
+        ];
+        let tree = build_tree(entries, Path::new("/home"));
+        assert_eq!(names(&tree), vec!["docs", "d.mp3", "e.txt"]);
+        let docs = tree.find(Path::new("/home/docs")).unwrap();
+        assert_eq!(names(docs), vec!["c.txt"]);
+    }
+
+    #[test]
+    fn should_compare_trees() {
+        let previous = vec![
+            entry("/home/a.txt", FileType::File),
+            entry("/home/b.txt", FileType::File),
+            entry("/home/docs", FileType::Directory),
+        ];
+        let mut modified = entry("/home/b.txt", FileType::File);
+        modified.metadata.size = 64;
+        let current = vec![
+            entry("/home/c.txt", FileType::File),
+            modified.clone(),
+            entry("/home/docs", FileType::Directory),
+        ];
+        let diff = compare_trees(&previous, &current);
+        assert_eq!(
+            diff,
+            TreeDiff {
+                added: vec![entry("/home/c.txt", FileType::File)],
+                removed: vec![entry("/home/a.txt", FileType::File)],
+                modified: vec![modified],
+            }
+        );
+        assert!(!diff.is_empty());
+        assert!(compare_trees(&previous, &previous).is_empty());
+    }
+
+    #[test]
+    fn should_rewrite_prefix() {
+        let mut entries = vec![
+            entry("/src", FileType::Directory),
+            entry("/src/a.txt", FileType::File),
+            entry("/src/docs", FileType::Directory),
+            entry("/src/docs/b.txt", FileType::File),
+            entry("/srcs/c.txt", FileType::File),
+            entry("/tmp/d.txt", FileType::File),
+        ];
+        rewrite_prefix(&mut entries, Path::new("/src"), Path::new("/backup/dst"));
+        let paths: Vec<&Path> = entries.iter().map(|entry| entry.path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("/backup/dst"),
+                Path::new("/backup/dst/a.txt"),
+                Path::new("/backup/dst/docs"),
+                Path::new("/backup/dst/docs/b.txt"),
+                Path::new("/srcs/c.txt"),
+                Path::new("/tmp/d.txt"),
+            ]
+        );
+        assert_eq!(entries[0].name(), "dst");
+        assert!(entries[0].is_dir());
+    }
+}
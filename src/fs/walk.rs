@@ -0,0 +1,73 @@
+//! ## Walk
+//!
+//! options for walking directories recursively
+
+/// Options for [`crate::RemoteFs::walk`]
+///
+/// The depth of an entry is the amount of directories between the walked directory and the entry,
+/// so the entries directly contained in the walked directory have depth `1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalkOptions {
+    /// Minimum depth of the returned entries.
+    /// Entries above this depth are still traversed, but they're not returned
+    pub min_depth: usize,
+    /// Maximum depth of the returned entries. Directories at this depth are not traversed
+    pub max_depth: usize,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            min_depth: 1,
+            max_depth: usize::MAX,
+        }
+    }
+}
+
+impl WalkOptions {
+    /// Construct options with minimum depth
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Construct options with maximum depth
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Returns whether an entry at `depth` must be returned
+    pub(crate) fn includes(&self, depth: usize) -> bool {
+        depth >= self.min_depth && depth <= self.max_depth
+    }
+
+    /// Returns whether a directory at `depth` must be traversed
+    pub(crate) fn descends(&self, depth: usize) -> bool {
+        depth < self.max_depth
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_create_walk_options() {
+        let options = WalkOptions::default();
+        assert_eq!(options.min_depth, 1);
+        assert_eq!(options.max_depth, usize::MAX);
+        let options = WalkOptions::default().min_depth(2).max_depth(3);
+        assert_eq!(options.min_depth, 2);
+        assert_eq!(options.max_depth, 3);
+        assert!(!options.includes(1));
+        assert!(options.includes(2));
+        assert!(options.includes(3));
+        assert!(!options.includes(4));
+        assert!(options.descends(2));
+        assert!(!options.descends(3));
+    }
+}
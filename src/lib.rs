@@ -26,7 +26,10 @@
 //!
 //! these features are supported:
 //!
+//! - `dir-entry`: enable `DirEntryLike`, a minimal view of a remote entry, to filter entries by path and kind.
 //! - `no-log`: disable logging. By default, this library will log via the `log` crate.
+//! - `serde`: enable the `fs::unix_pex_octal` module, to (de)serialize `UnixPex` as an octal string.
+//! - `trace-fs`: enable `TracingFs`, a `RemoteFs` wrapper which logs each operation via the `log` crate (not `tracing` spans).
 
 #![doc(html_playground_url = "https://play.rust-lang.org")]
 #![doc(
@@ -44,6 +47,7 @@ extern crate log;
 pub use fs::{File, RemoteError, RemoteErrorType, RemoteFs, RemoteResult};
 // -- modules
 pub mod fs;
+pub mod path;
 
 // -- utils
 pub(crate) mod utils;
@@ -0,0 +1,717 @@
+//! ## Memory
+//!
+//! An in-memory remote file system to test the default implementations of `RemoteFs`
+
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::fs::stream::ReadAndSeek;
+use crate::fs::{
+    ExecOutput, FileType, HostKey, HostKeyDecision, HostKeyVerifier, Metadata, ReadStream, UnixPex,
+    Welcome, WriteStream, DEFAULT_BUFFER_SIZE,
+};
+use crate::utils::path::absolutize;
+use crate::{File, RemoteError, RemoteErrorType, RemoteFs, RemoteResult};
+
+impl ReadAndSeek for Cursor<Vec<u8>> {}
+
+/// A node in the memory file system
+#[derive(Default)]
+struct Node {
+    metadata: Metadata,
+    content: Arc<Mutex<Vec<u8>>>,
+    /// Alternate data streams, by name
+    streams: BTreeMap<String, Arc<Mutex<Vec<u8>>>>,
+}
+
+/// In-memory file system.
+///
+/// Cloning a `MemoryFs` creates a new session sharing the same file tree.
+#[derive(Clone)]
+pub struct MemoryFs {
+    tree: Arc<Mutex<BTreeMap<PathBuf, Node>>>,
+    connected: bool,
+    wrkdir: PathBuf,
+    /// Paths passed to `fsync`
+    fsync_calls: Arc<Mutex<Vec<PathBuf>>>,
+    /// Whether `list_dir` reports the target of symlinks
+    list_symlink_targets: bool,
+    /// Whether `copy` is supported
+    copy: bool,
+    /// Whether `mov` is supported
+    mov: bool,
+    /// Whether `setstat` is supported
+    setstat: bool,
+    /// Whether files can be opened for reading
+    readable: bool,
+    /// Key presented by the server on connect
+    host_key: Option<HostKey>,
+    host_key_verifier: Arc<Mutex<Option<HostKeyVerifier>>>,
+    stored_host_keys: Arc<Mutex<Vec<HostKey>>>,
+    /// Bits cleared from the mode of the created directories
+    umask: u32,
+    /// Size of the buffer returned by `buffer_size`
+    buffer_size: Option<usize>,
+    /// Size of each write to a file
+    write_sizes: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[allow(dead_code)]
+impl MemoryFs {
+    /// Instantiates a new connected `MemoryFs` containing only the root directory
+    pub fn new() -> Self {
+        let mut tree = BTreeMap::new();
+        tree.insert(
+            PathBuf::from("/"),
+            Node {
+                metadata: Metadata::default()
+                    .file_type(FileType::Directory)
+                    .mode(UnixPex::from(0o755)),
+                ..Default::default()
+            },
+        );
+        Self {
+            tree: Arc::new(Mutex::new(tree)),
+            connected: true,
+            wrkdir: PathBuf::from("/"),
+            fsync_calls: Arc::default(),
+            list_symlink_targets: true,
+            copy: true,
+            mov: true,
+            setstat: true,
+            readable: true,
+            host_key: None,
+            host_key_verifier: Arc::default(),
+            stored_host_keys: Arc::default(),
+            umask: 0,
+            buffer_size: None,
+            write_sizes: Arc::default(),
+        }
+    }
+
+    /// Present `key` as the server host key on connect
+    pub fn with_host_key(mut self, key: HostKey) -> Self {
+        self.host_key = Some(key);
+        self
+    }
+
+    /// Get the host keys stored by the host key verifier
+    pub fn stored_host_keys(&self) -> Vec<HostKey> {
+        self.stored_host_keys.lock().unwrap().clone()
+    }
+
+    /// Clear the bits of `umask` from the mode of the created directories, as servers do
+    pub fn with_umask(mut self, umask: u32) -> Self {
+        self.umask = umask;
+        self
+    }
+
+    /// Use a transfer buffer of `size` bytes
+    pub fn with_buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = Some(size);
+        self
+    }
+
+    /// Get the size of each write to a file, in order
+    pub fn write_sizes(&self) -> Vec<usize> {
+        self.write_sizes.lock().unwrap().clone()
+    }
+
+    /// Make `list_dir` omit the target of symlinks, as protocols which need a request to read each link
+    pub fn without_symlink_targets_in_listing(mut self) -> Self {
+        self.list_symlink_targets = false;
+        self
+    }
+
+    /// Make `copy` unsupported, as on servers which can't copy files
+    pub fn without_copy(mut self) -> Self {
+        self.copy = false;
+        self
+    }
+
+    /// Simulate the connection being dropped by the server, without disconnecting
+    pub fn drop_connection(&mut self) {
+        self.connected = false;
+    }
+
+    /// Make `setstat` unsupported, as on servers which can't change the metadata of files
+    pub fn without_setstat(mut self) -> Self {
+        self.setstat = false;
+        self
+    }
+
+    /// Make `open` fail, as on servers denying read access to files
+    pub fn without_read_access(mut self) -> Self {
+        self.readable = false;
+        self
+    }
+
+    /// Make `mov` fail, as it happens when moving files across devices
+    pub fn without_mov(mut self) -> Self {
+        self.mov = false;
+        self
+    }
+
+    /// Add a directory to the file system
+    pub fn with_dir<P: AsRef<Path>>(self, path: P) -> Self {
+        self.insert(
+            path.as_ref(),
+            Metadata::default()
+                .file_type(FileType::Directory)
+                .mode(UnixPex::from(0o755)),
+            vec![],
+        );
+        self
+    }
+
+    /// Add a file with the provided content to the file system
+    pub fn with_file<P: AsRef<Path>>(self, path: P, content: &[u8]) -> Self {
+        self.insert(
+            path.as_ref(),
+            Metadata::default().mode(UnixPex::from(0o644)),
+            content.to_vec(),
+        );
+        self
+    }
+
+    /// Add a symlink pointing to `target` to the file system
+    pub fn with_symlink<P: AsRef<Path>, T: AsRef<Path>>(self, path: P, target: T) -> Self {
+        self.insert(
+            path.as_ref(),
+            Metadata::default()
+                .file_type(FileType::Symlink)
+                .symlink(target),
+            vec![],
+        );
+        self
+    }
+
+    /// Add a symlink at `path` whose target can't be read
+    pub fn with_unreadable_symlink<P: AsRef<Path>>(self, path: P) -> Self {
+        self.insert(
+            path.as_ref(),
+            Metadata::default().file_type(FileType::Symlink),
+            vec![],
+        );
+        self
+    }
+
+    /// Set the device id of the file at `path`
+    pub fn with_dev<P: AsRef<Path>>(self, path: P, dev: u64) -> Self {
+        if let Some(node) = self.tree.lock().unwrap().get_mut(path.as_ref()) {
+            node.metadata.dev = Some(dev);
+        }
+        self
+    }
+
+    /// Add an alternate data stream named `name` to the file at `path`
+    pub fn with_named_stream<P: AsRef<Path>>(self, path: P, name: &str, content: &[u8]) -> Self {
+        if let Some(node) = self.tree.lock().unwrap().get_mut(path.as_ref()) {
+            node.streams
+                .insert(name.to_string(), Arc::new(Mutex::new(content.to_vec())));
+        }
+        self
+    }
+
+    /// Get the content of the file at `path`
+    pub fn content<P: AsRef<Path>>(&self, path: P) -> Option<Vec<u8>> {
+        self.tree
+            .lock()
+            .unwrap()
+            .get(path.as_ref())
+            .map(|node| node.content.lock().unwrap().clone())
+    }
+
+    /// Get the paths `fsync` has been called on
+    pub fn fsync_calls(&self) -> Vec<PathBuf> {
+        self.fsync_calls.lock().unwrap().clone()
+    }
+
+    fn insert(&self, path: &Path, metadata: Metadata, content: Vec<u8>) {
+        self.tree.lock().unwrap().insert(
+            path.to_path_buf(),
+            Node {
+                metadata,
+                content: Arc::new(Mutex::new(content)),
+                ..Default::default()
+            },
+        );
+    }
+
+    fn check_connection(&self) -> RemoteResult<()> {
+        match self.connected {
+            true => Ok(()),
+            false => Err(RemoteError::new(RemoteErrorType::NotConnected)),
+        }
+    }
+
+    fn path(&self, path: &Path) -> PathBuf {
+        absolutize(self.wrkdir.as_path(), path)
+    }
+
+    fn entry(&self, path: &Path) -> RemoteResult<File> {
+        let tree = self.tree.lock().unwrap();
+        let node = tree
+            .get(path)
+            .ok_or_else(|| RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory))?;
+        let mut metadata = node.metadata.clone();
+        if metadata.is_file() {
+            metadata.size = node.content.lock().unwrap().len() as u64;
+        }
+        if !node.streams.is_empty() {
+            metadata.streams = Some(node.streams.keys().cloned().collect());
+        }
+        Ok(File {
+            path: path.to_path_buf(),
+            metadata,
+        })
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.tree
+            .lock()
+            .unwrap()
+            .get(path)
+            .map(|node| node.metadata.is_dir())
+            .unwrap_or(false)
+    }
+
+    fn check_parent(&self, path: &Path) -> RemoteResult<()> {
+        match path.parent() {
+            Some(parent) if !self.is_dir(parent) => {
+                Err(RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn children(&self, path: &Path) -> Vec<PathBuf> {
+        self.tree
+            .lock()
+            .unwrap()
+            .keys()
+            .filter(|p| p.parent() == Some(path))
+            .cloned()
+            .collect()
+    }
+
+    fn writer(
+        &self,
+        path: &Path,
+        metadata: &Metadata,
+        truncate: bool,
+    ) -> RemoteResult<WriteStream> {
+        self.check_connection()?;
+        let path = self.path(path);
+        self.check_parent(path.as_path())?;
+        let mut tree = self.tree.lock().unwrap();
+        let node = tree.entry(path).or_insert_with(|| Node {
+            metadata: metadata.clone().file_type(FileType::File),
+            ..Default::default()
+        });
+        if !node.metadata.is_file() {
+            return Err(RemoteError::new(RemoteErrorType::BadFile));
+        }
+        if truncate {
+            node.content.lock().unwrap().clear();
+        }
+        let writer: Box<dyn Write + Send> = Box::new(MemoryWriter {
+            content: node.content.clone(),
+            write_sizes: self.write_sizes.clone(),
+        });
+        Ok(WriteStream::from(writer))
+    }
+}
+
+/// Replace the `src` prefix of `path` with `dest`
+fn rebase(path: &Path, src: &Path, dest: &Path) -> PathBuf {
+    match path.strip_prefix(src) {
+        Ok(rel) if rel.as_os_str().is_empty() => dest.to_path_buf(),
+        Ok(rel) => dest.join(rel),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Metadata of a copy of a file with `metadata`.
+/// As `cp` without `-p`, times and permissions are not preserved
+fn copied_metadata(metadata: &Metadata) -> Metadata {
+    let mode = match metadata.is_dir() {
+        true => 0o755,
+        false => 0o644,
+    };
+    Metadata {
+        accessed: None,
+        created: None,
+        modified: None,
+        mode: Some(UnixPex::from(mode)),
+        ..metadata.clone()
+    }
+}
+
+/// Writer which appends data to the content of a [`Node`]
+struct MemoryWriter {
+    content: Arc<Mutex<Vec<u8>>>,
+    write_sizes: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Write for MemoryWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.content.lock().unwrap().extend_from_slice(buf);
+        self.write_sizes.lock().unwrap().push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl RemoteFs for MemoryFs {
+    fn connect(&mut self) -> RemoteResult<Welcome> {
+        if self.connected {
+            return Err(RemoteError::new(RemoteErrorType::AlreadyConnected));
+        }
+        if let (Some(key), Some(verifier)) = (
+            self.host_key.as_ref(),
+            self.host_key_verifier.lock().unwrap().as_mut(),
+        ) {
+            match verifier(key) {
+                HostKeyDecision::Accept => {}
+                HostKeyDecision::AcceptAndStore => {
+                    self.stored_host_keys.lock().unwrap().push(key.clone())
+                }
+                HostKeyDecision::Reject => {
+                    return Err(RemoteError::new_ex(
+                        RemoteErrorType::HostKeyMismatch,
+                        format!("untrusted key {}", key.fingerprint()),
+                    ))
+                }
+            }
+        }
+        self.connected = true;
+        self.wrkdir = PathBuf::from("/");
+        Ok(Welcome::default())
+    }
+
+    fn set_host_key_verifier(&mut self, verifier: HostKeyVerifier) {
+        *self.host_key_verifier.lock().unwrap() = Some(verifier);
+    }
+
+    fn disconnect(&mut self) -> RemoteResult<()> {
+        self.check_connection()?;
+        self.connected = false;
+        Ok(())
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.connected
+    }
+
+    fn last_working_dir(&self) -> Option<PathBuf> {
+        Some(self.wrkdir.clone())
+    }
+
+    fn pwd(&mut self) -> RemoteResult<PathBuf> {
+        self.check_connection()?;
+        Ok(self.wrkdir.clone())
+    }
+
+    fn change_dir(&mut self, dir: &Path) -> RemoteResult<PathBuf> {
+        self.check_connection()?;
+        let dir = self.path(dir);
+        if !self.is_dir(dir.as_path()) {
+            return Err(RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory));
+        }
+        self.wrkdir = dir.clone();
+        Ok(dir)
+    }
+
+    fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>> {
+        self.check_connection()?;
+        let path = self.path(path);
+        if !self.is_dir(path.as_path()) {
+            return Err(RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory));
+        }
+        self.children(path.as_path())
+            .iter()
+            .map(|p| {
+                let mut entry = self.entry(p)?;
+                if !self.list_symlink_targets {
+                    entry.metadata.symlink = None;
+                }
+                Ok(entry)
+            })
+            .collect()
+    }
+
+    fn stat(&mut self, path: &Path) -> RemoteResult<File> {
+        self.check_connection()?;
+        let path = self.path(path);
+        self.entry(path.as_path())
+    }
+
+    fn setstat(&mut self, path: &Path, metadata: Metadata) -> RemoteResult<()> {
+        self.check_connection()?;
+        if !self.setstat {
+            return Err(RemoteError::new(RemoteErrorType::UnsupportedFeature));
+        }
+        let path = self.path(path);
+        let mut tree = self.tree.lock().unwrap();
+        let node = tree
+            .get_mut(&path)
+            .ok_or_else(|| RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory))?;
+        // unset fields are left unchanged, as SFTP does
+        let current = &mut node.metadata;
+        current.accessed = metadata.accessed.or(current.accessed);
+        current.created = metadata.created.or(current.created);
+        current.modified = metadata.modified.or(current.modified);
+        current.mode = metadata.mode.or(current.mode);
+        current.uid = metadata.uid.or(current.uid);
+        current.gid = metadata.gid.or(current.gid);
+        Ok(())
+    }
+
+    fn exists(&mut self, path: &Path) -> RemoteResult<bool> {
+        self.check_connection()?;
+        let path = self.path(path);
+        Ok(self.tree.lock().unwrap().contains_key(&path))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> RemoteResult<()> {
+        self.check_connection()?;
+        let path = self.path(path);
+        if self.entry(path.as_path())?.is_dir() {
+            return Err(RemoteError::new(RemoteErrorType::BadFile));
+        }
+        self.tree.lock().unwrap().remove(&path);
+        Ok(())
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> RemoteResult<()> {
+        self.check_connection()?;
+        let path = self.path(path);
+        if !self.entry(path.as_path())?.is_dir() {
+            return Err(RemoteError::new(RemoteErrorType::BadFile));
+        }
+        if !self.children(path.as_path()).is_empty() {
+            return Err(RemoteError::new(RemoteErrorType::DirectoryNotEmpty));
+        }
+        self.tree.lock().unwrap().remove(&path);
+        Ok(())
+    }
+
+    fn create_dir(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()> {
+        self.check_connection()?;
+        let path = self.path(path);
+        if self.tree.lock().unwrap().contains_key(&path) {
+            return Err(RemoteError::new(RemoteErrorType::DirectoryAlreadyExists));
+        }
+        self.check_parent(path.as_path())?;
+        self.insert(
+            path.as_path(),
+            Metadata::default()
+                .file_type(FileType::Directory)
+                .mode(UnixPex::from(u32::from(mode) & !self.umask)),
+            vec![],
+        );
+        Ok(())
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> RemoteResult<()> {
+        self.check_connection()?;
+        let path = self.path(path);
+        self.check_parent(path.as_path())?;
+        self.insert(
+            path.as_path(),
+            Metadata::default()
+                .file_type(FileType::Symlink)
+                .symlink(target),
+            vec![],
+        );
+        Ok(())
+    }
+
+    fn copy(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.check_connection()?;
+        if !self.copy {
+            return Err(RemoteError::new(RemoteErrorType::UnsupportedFeature));
+        }
+        let src = self.path(src);
+        let dest = self.path(dest);
+        self.check_parent(dest.as_path())?;
+        let mut tree = self.tree.lock().unwrap();
+        let copies: Vec<(PathBuf, Node)> = tree
+            .iter()
+            .filter(|(p, _)| p.starts_with(&src))
+            .map(|(p, node)| {
+                (
+                    rebase(p, src.as_path(), dest.as_path()),
+                    Node {
+                        metadata: copied_metadata(&node.metadata),
+                        content: Arc::new(Mutex::new(node.content.lock().unwrap().clone())),
+                        streams: node
+                            .streams
+                            .iter()
+                            .map(|(name, content)| {
+                                let content = content.lock().unwrap().clone();
+                                (name.clone(), Arc::new(Mutex::new(content)))
+                            })
+                            .collect(),
+                    },
+                )
+            })
+            .collect();
+        if copies.is_empty() {
+            return Err(RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory));
+        }
+        tree.extend(copies);
+        Ok(())
+    }
+
+    fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.check_connection()?;
+        if !self.mov {
+            return Err(RemoteError::new(RemoteErrorType::UnsupportedFeature));
+        }
+        let src = self.path(src);
+        let dest = self.path(dest);
+        self.check_parent(dest.as_path())?;
+        let mut tree = self.tree.lock().unwrap();
+        // files can't be renamed to a different device
+        let dev = |path: &Path| tree.get(path).and_then(|node| node.metadata.dev);
+        if let (Some(src_dev), Some(dest_dev)) = (dev(&src), dest.parent().and_then(dev)) {
+            if src_dev != dest_dev {
+                return Err(RemoteError::new(RemoteErrorType::CrossDevice));
+            }
+        }
+        let moved: Vec<PathBuf> = tree
+            .keys()
+            .filter(|p| p.starts_with(&src))
+            .cloned()
+            .collect();
+        if moved.is_empty() {
+            return Err(RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory));
+        }
+        for p in moved {
+            let node = tree.remove(&p).unwrap();
+            tree.insert(rebase(p.as_path(), src.as_path(), dest.as_path()), node);
+        }
+        Ok(())
+    }
+
+    fn exec(&mut self, _cmd: &str) -> RemoteResult<(u32, String)> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    /// Supports `cat > PATH`, which writes the whole input to `PATH`,
+    /// and `head -c N > PATH`, which writes the first `N` bytes of the input to `PATH` and closes stdin
+    fn exec_with_stdin(
+        &mut self,
+        cmd: &str,
+        stdin: Box<dyn Read + Send>,
+    ) -> RemoteResult<ExecOutput> {
+        let args: Vec<&str> = cmd.split_whitespace().collect();
+        let (limit, path) = match args.as_slice() {
+            ["cat", ">", path] => (u64::MAX, path),
+            ["head", "-c", n, ">", path] => match n.parse() {
+                Ok(n) => (n, path),
+                Err(_) => {
+                    return Ok(ExecOutput {
+                        exit_code: 1,
+                        ..Default::default()
+                    })
+                }
+            },
+            _ => {
+                return Ok(ExecOutput {
+                    exit_code: 127,
+                    stdout: format!("{cmd}: command not found"),
+                    stdin_written: 0,
+                })
+            }
+        };
+        let mut stream = self.writer(Path::new(path), &Metadata::default(), true)?;
+        let stdin_written = std::io::copy(&mut stdin.take(limit), &mut stream)
+            .map_err(|e| RemoteError::new_ex(RemoteErrorType::ProtocolError, e.to_string()))?;
+        Ok(ExecOutput {
+            exit_code: 0,
+            stdout: String::new(),
+            stdin_written,
+        })
+    }
+
+    fn append(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.writer(path, metadata, false)
+    }
+
+    fn create(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.writer(path, metadata, true)
+    }
+
+    fn open(&mut self, path: &Path) -> RemoteResult<ReadStream> {
+        self.check_connection()?;
+        let path = self.path(path);
+        if !self.entry(path.as_path())?.is_file() {
+            return Err(RemoteError::new(RemoteErrorType::BadFile));
+        }
+        if !self.readable {
+            return Err(RemoteError::new(RemoteErrorType::PexError));
+        }
+        let content = self.content(path).unwrap_or_default();
+        let reader: Box<dyn ReadAndSeek> = Box::new(Cursor::new(content));
+        Ok(ReadStream::from(reader))
+    }
+
+    fn buffer_size(&self) -> usize {
+        self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE)
+    }
+
+    fn fsync(&mut self, path: &Path) -> RemoteResult<()> {
+        self.check_connection()?;
+        let path = self.path(path);
+        if !self.entry(path.as_path())?.is_file() {
+            return Err(RemoteError::new(RemoteErrorType::BadFile));
+        }
+        self.fsync_calls.lock().unwrap().push(path);
+        Ok(())
+    }
+
+    fn open_named_stream(&mut self, path: &Path, stream: &str) -> RemoteResult<ReadStream> {
+        self.check_connection()?;
+        let path = self.path(path);
+        let tree = self.tree.lock().unwrap();
+        let content = tree
+            .get(&path)
+            .and_then(|node| node.streams.get(stream))
+            .ok_or_else(|| RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory))?
+            .lock()
+            .unwrap()
+            .clone();
+        let reader: Box<dyn ReadAndSeek> = Box::new(Cursor::new(content));
+        Ok(ReadStream::from(reader))
+    }
+
+    fn create_named_stream(&mut self, path: &Path, stream: &str) -> RemoteResult<WriteStream> {
+        self.check_connection()?;
+        let path = self.path(path);
+        let mut tree = self.tree.lock().unwrap();
+        let node = tree
+            .get_mut(&path)
+            .ok_or_else(|| RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory))?;
+        let content: Arc<Mutex<Vec<u8>>> = Arc::default();
+        node.streams.insert(stream.to_string(), content.clone());
+        let writer: Box<dyn Write + Send> = Box::new(MemoryWriter {
+            content,
+            write_sizes: self.write_sizes.clone(),
+        });
+        Ok(WriteStream::from(writer))
+    }
+}
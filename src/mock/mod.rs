@@ -4,6 +4,10 @@
 
 use crate::RemoteFs;
 
+mod memory;
+
+pub use memory::MemoryFs;
+
 // -- mock
 pub struct MockRemoteFs;
 
@@ -35,7 +39,14 @@ impl RemoteFs for MockRemoteFs {
 
     #[allow(unused)]
     fn list_dir(&mut self, path: &std::path::Path) -> crate::RemoteResult<Vec<crate::File>> {
-        Ok(vec![])
+        // entries are intentionally not sorted
+        Ok(["c.txt", "a.txt", "b.txt"]
+            .iter()
+            .map(|name| crate::File {
+                path: path.join(name),
+                metadata: crate::fs::Metadata::default(),
+            })
+            .collect())
     }
 
     #[allow(unused)]
@@ -0,0 +1,5 @@
+//! ## Path
+//!
+//! `path` is the module which exposes utilities to handle remote paths
+
+pub use crate::utils::path::{containing_root, sanitize_for_local, truncate_middle};
@@ -2,4 +2,5 @@
 //!
 //! `utils` is the module which provides utilities of different kind
 
+pub mod digest;
 pub mod path;
@@ -2,7 +2,7 @@
 //!
 //! path utilities
 
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// Absolutize target path if relative.
 pub fn absolutize(wrkdir: &Path, target: &Path) -> PathBuf {
@@ -16,7 +16,128 @@ pub fn absolutize(wrkdir: &Path, target: &Path) -> PathBuf {
     }
 }
 
+/// Normalize `path` collapsing repeated separators, `.` components and trailing separators.
+///
+/// `..` components are kept, since they can't be resolved without knowing whether the parent is a symlink.
+pub fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// Get the most specific root among `roots` containing `path`, if any.
+///
+/// A root contains `path` if `path` is the root itself or one of its descendants;
+/// when several roots contain `path` (e.g. `/home` and `/home/omar`), the deepest one is returned.
+/// Paths are compared component-wise, so `/home/omar` doesn't contain `/home/omarino`
+pub fn containing_root<'a>(path: &Path, roots: &'a [PathBuf]) -> Option<&'a Path> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .map(PathBuf::as_path)
+}
+
+/// Shorten `path` to at most `max_chars` characters, replacing the middle of the path with `...`,
+/// such as `/very/long/.../file.txt`, to render it in a fixed-width column.
+///
+/// The file name is kept, preceded by as many leading components as fit. Paths which already fit are unchanged.
+/// If not even the file name fits, the end of the file name is kept (e.g. `...e.txt`),
+/// and limits too small for the ellipsis return as many dots as fit.
+pub fn truncate_middle(path: &Path, max_chars: usize) -> String {
+    const ELLIPSIS: &str = "...";
+    let full: Vec<char> = path.to_string_lossy().chars().collect();
+    if full.len() <= max_chars {
+        return full.into_iter().collect();
+    }
+    if max_chars <= ELLIPSIS.len() {
+        return ".".repeat(max_chars);
+    }
+    let name_len = path
+        .file_name()
+        .map(|name| name.to_string_lossy().chars().count())
+        .unwrap_or_default();
+    // the file name and its leading separator
+    let suffix_len = (name_len + 1).min(full.len());
+    let suffix: String = full[full.len() - suffix_len..].iter().collect();
+    if suffix_len + ELLIPSIS.len() > max_chars {
+        let tail: String = full[full.len() - (max_chars - ELLIPSIS.len())..]
+            .iter()
+            .collect();
+        return format!("{ELLIPSIS}{tail}");
+    }
+    // keep the leading components which fit before `/...`
+    let parent = &full[..full.len() - suffix_len];
+    let budget = max_chars.saturating_sub(suffix_len + ELLIPSIS.len() + 1);
+    let prefix_len = parent
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(i, c)| **c == '/' && *i <= budget)
+        .map(|(i, _)| i)
+        .next_back();
+    match prefix_len {
+        Some(len) => {
+            let prefix: String = parent[..len].iter().collect();
+            format!("{prefix}/{ELLIPSIS}{suffix}")
+        }
+        None => format!("{ELLIPSIS}{suffix}"),
+    }
+}
+
+/// Sanitize the file `name` of a remote file, so that it can be used as a file name on the local file system.
+///
+/// Characters which are not allowed in file names are replaced with `_`, and so are the `.` and `..` names.
+/// On Windows, names reserved by the system (such as `CON` or `LPT1.txt`) are prefixed with `_`,
+/// and trailing dots and spaces, which Windows would strip, are replaced with `_`.
+pub fn sanitize_for_local(name: &str) -> String {
+    if name.is_empty() || name == "." || name == ".." {
+        return String::from("_");
+    }
+    let sanitized: String = name
+        .chars()
+        .map(|c| match is_illegal_char(c) {
+            true => '_',
+            false => c,
+        })
+        .collect();
+    #[cfg(target_family = "windows")]
+    let sanitized = sanitize_for_windows(sanitized);
+    sanitized
+}
+
+#[cfg(target_family = "windows")]
+fn is_illegal_char(c: char) -> bool {
+    matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') || c.is_control()
+}
+
+#[cfg(not(target_family = "windows"))]
+fn is_illegal_char(c: char) -> bool {
+    matches!(c, '/' | '\0')
+}
+
+#[cfg(target_family = "windows")]
+fn sanitize_for_windows(mut name: String) -> String {
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        name.insert(0, '_');
+    }
+    if name.ends_with(['.', ' ']) {
+        name.pop();
+        name.push('_');
+    }
+    name
+}
+
 #[cfg(test)]
+#[allow(clippy::needless_borrow)]
 mod test {
 
     use super::*;
@@ -32,4 +153,102 @@ mod test {
             Path::new("/tmp/readme.txt")
         );
     }
+
+    #[test]
+    fn normalize_path() {
+        assert_eq!(normalize(Path::new("/a//b")).as_os_str(), "/a/b");
+        assert_eq!(normalize(Path::new("/a/./b/")).as_os_str(), "/a/b");
+        assert_eq!(normalize(Path::new("./a/b")).as_os_str(), "a/b");
+        assert_eq!(normalize(Path::new("/a/../b")).as_os_str(), "/a/../b");
+    }
+
+    #[test]
+    fn get_containing_root() {
+        let roots = vec![
+            PathBuf::from("/home"),
+            PathBuf::from("/home/omar/projects"),
+            PathBuf::from("/home/omar"),
+            PathBuf::from("/tmp"),
+        ];
+        assert_eq!(
+            containing_root(Path::new("/home/omar/projects/remotefs/src"), &roots),
+            Some(Path::new("/home/omar/projects"))
+        );
+        assert_eq!(
+            containing_root(Path::new("/home/omar/readme.txt"), &roots),
+            Some(Path::new("/home/omar"))
+        );
+        assert_eq!(
+            containing_root(Path::new("/home/omar"), &roots),
+            Some(Path::new("/home/omar"))
+        );
+        assert_eq!(
+            containing_root(Path::new("/home/omarino"), &roots),
+            Some(Path::new("/home"))
+        );
+        assert_eq!(containing_root(Path::new("/var/log"), &roots), None);
+        assert_eq!(containing_root(Path::new("/tmp/a"), &[]), None);
+    }
+
+    #[test]
+    fn truncate_path_in_the_middle() {
+        let path = Path::new("/very/long/path/to/file.txt");
+        assert_eq!(truncate_middle(path, 27), "/very/long/path/to/file.txt");
+        assert_eq!(truncate_middle(path, 40), "/very/long/path/to/file.txt");
+        assert_eq!(truncate_middle(path, 26), "/very/long/.../file.txt");
+        assert_eq!(truncate_middle(path, 20), "/very/.../file.txt");
+        assert_eq!(truncate_middle(path, 17), ".../file.txt");
+        assert_eq!(truncate_middle(path, 12), ".../file.txt");
+        assert_eq!(truncate_middle(path, 8), "...e.txt");
+        assert_eq!(truncate_middle(path, 3), "...");
+        assert_eq!(truncate_middle(path, 2), "..");
+        assert_eq!(truncate_middle(path, 0), "");
+        assert_eq!(
+            truncate_middle(Path::new("a/b/c/d/file.txt"), 14),
+            "a/.../file.txt"
+        );
+        assert_eq!(
+            truncate_middle(Path::new("/ünïcödé/ä/b.txt"), 12),
+            ".../b.txt"
+        );
+    }
+
+    #[test]
+    fn sanitize_name_for_local() {
+        assert_eq!(sanitize_for_local("readme.txt"), "readme.txt");
+        assert_eq!(sanitize_for_local("a/b"), "a_b");
+        assert_eq!(sanitize_for_local(""), "_");
+        assert_eq!(sanitize_for_local("."), "_");
+        assert_eq!(sanitize_for_local(".."), "_");
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn sanitize_name_for_unix() {
+        assert_eq!(sanitize_for_local("a:b?*.txt"), "a:b?*.txt");
+        assert_eq!(sanitize_for_local("CON"), "CON");
+        assert_eq!(sanitize_for_local("a\0b"), "a_b");
+        assert_eq!(sanitize_for_local("..."), "...");
+    }
+
+    #[test]
+    #[cfg(target_family = "windows")]
+    fn sanitize_illegal_chars_for_windows() {
+        assert_eq!(sanitize_for_local("a:b?*.txt"), "a_b__.txt");
+        assert_eq!(sanitize_for_local("<a>|\"b\"\\c"), "_a___b__c");
+        assert_eq!(sanitize_for_local("new\nline"), "new_line");
+        assert_eq!(sanitize_for_local("trailing."), "trailing_");
+        assert_eq!(sanitize_for_local("trailing "), "trailing_");
+    }
+
+    #[test]
+    #[cfg(target_family = "windows")]
+    fn sanitize_reserved_names_for_windows() {
+        assert_eq!(sanitize_for_local("CON"), "_CON");
+        assert_eq!(sanitize_for_local("con"), "_con");
+        assert_eq!(sanitize_for_local("LPT1.txt"), "_LPT1.txt");
+        assert_eq!(sanitize_for_local("aux.tar.gz"), "_aux.tar.gz");
+        assert_eq!(sanitize_for_local("CONSOLE"), "CONSOLE");
+        assert_eq!(sanitize_for_local("COM10"), "COM10");
+    }
 }